@@ -7,11 +7,20 @@ use sea_orm::entity::prelude::*;
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
+    /// Random 64-bit document key, also the key of its vector. Unlike `id`
+    /// it stays unique when databases are merged. Stored signed; use
+    /// `key as u64` for the vector key.
+    #[sea_orm(unique)]
+    pub key: i64,
     pub title: String,
     pub text: String,
     pub source: String,
     pub url: Option<String>,
     pub created_at: String,
+    /// Little-endian f32 vector, as embedded by `embedding_model`.
+    #[sea_orm(column_type = "Binary(BlobSize::Blob(None))", nullable)]
+    pub embedding: Option<Vec<u8>>,
+    pub embedding_model: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -1,2 +1,5 @@
-
+pub mod alert;
 pub mod content;
+pub mod saved_search;
+pub mod search_log;
+pub mod setting;
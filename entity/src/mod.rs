@@ -2,4 +2,8 @@
 
 pub mod prelude;
 
+pub mod alert;
 pub mod content;
+pub mod saved_search;
+pub mod search_log;
+pub mod setting;
@@ -1,3 +1,7 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.10
 
+pub use super::alert::Entity as Alert;
 pub use super::content::Entity as Content;
+pub use super::saved_search::Entity as SavedSearch;
+pub use super::search_log::Entity as SearchLog;
+pub use super::setting::Entity as Setting;
@@ -1,12 +1,26 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20240101_000002_add_embedding;
+mod m20240101_000003_add_content_key;
+mod m20240101_000004_add_search_history;
+mod m20240101_000005_add_alerts;
+mod m20240101_000006_delete_alerts_with_content;
+mod m20240101_000007_add_settings;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20240101_000002_add_embedding::Migration),
+            Box::new(m20240101_000003_add_content_key::Migration),
+            Box::new(m20240101_000004_add_search_history::Migration),
+            Box::new(m20240101_000005_add_alerts::Migration),
+            Box::new(m20240101_000006_delete_alerts_with_content::Migration),
+            Box::new(m20240101_000007_add_settings::Migration),
+        ]
     }
 }
@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Content::Table)
+                    .add_column(
+                        ColumnDef::new(Content::Key)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Existing rows get random keys like new ones. Their vectors are keyed
+        // by row id, so the index is rebuilt on the next start.
+        manager
+            .get_connection()
+            .execute_unprepared(r#"UPDATE "content" SET "key" = random()"#)
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_content_key")
+                    .table(Content::Table)
+                    .col(Content::Key)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_content_key")
+                    .table(Content::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Content::Table)
+                    .drop_column(Content::Key)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Content {
+    Table,
+    Key,
+}
@@ -0,0 +1,87 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SearchLog::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SearchLog::Id)
+                            .not_null()
+                            .integer()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SearchLog::Query).string().not_null())
+                    .col(ColumnDef::new(SearchLog::Filters).string().not_null())
+                    .col(ColumnDef::new(SearchLog::CreatedAt).timestamp().not_null())
+                    .col(ColumnDef::new(SearchLog::Clicked).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SavedSearch::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SavedSearch::Id)
+                            .not_null()
+                            .integer()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SavedSearch::Name).string().not_null())
+                    .col(ColumnDef::new(SavedSearch::Query).string().not_null())
+                    .col(
+                        ColumnDef::new(SavedSearch::Pinned)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(SavedSearch::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SavedSearch::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(SearchLog::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SearchLog {
+    Table,
+    Id,
+    Query,
+    Filters,
+    CreatedAt,
+    Clicked,
+}
+
+#[derive(DeriveIden)]
+enum SavedSearch {
+    Table,
+    Id,
+    Name,
+    Query,
+    Pinned,
+    CreatedAt,
+}
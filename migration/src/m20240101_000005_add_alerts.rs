@@ -0,0 +1,98 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only adds one column per statement.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SavedSearch::Table)
+                    .add_column(
+                        ColumnDef::new(SavedSearch::Alert)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SavedSearch::Table)
+                    .add_column(ColumnDef::new(SavedSearch::AlertThreshold).float().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Alert::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Alert::Id)
+                            .not_null()
+                            .integer()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Alert::SavedSearchId).integer().not_null())
+                    .col(ColumnDef::new(Alert::ContentKey).big_integer().not_null())
+                    .col(ColumnDef::new(Alert::Similarity).float().not_null())
+                    .col(ColumnDef::new(Alert::CreatedAt).timestamp().not_null())
+                    .col(
+                        ColumnDef::new(Alert::Seen)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Alert::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SavedSearch::Table)
+                    .drop_column(SavedSearch::AlertThreshold)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SavedSearch::Table)
+                    .drop_column(SavedSearch::Alert)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SavedSearch {
+    Table,
+    Alert,
+    AlertThreshold,
+}
+
+#[derive(DeriveIden)]
+enum Alert {
+    Table,
+    Id,
+    SavedSearchId,
+    ContentKey,
+    Similarity,
+    CreatedAt,
+    Seen,
+}
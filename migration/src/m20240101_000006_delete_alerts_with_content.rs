@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_alert_content_key")
+                    .table(Alert::Table)
+                    .col(Alert::ContentKey)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Pages may be deleted from outside semtex, so the database deletes
+        // their alerts itself.
+        let connection = manager.get_connection();
+        connection
+            .execute_unprepared(
+                r#"DELETE FROM "alert" WHERE "content_key" NOT IN (SELECT "key" FROM "content")"#,
+            )
+            .await?;
+        connection
+            .execute_unprepared(
+                r#"CREATE TRIGGER IF NOT EXISTS "delete_alerts_with_content"
+                AFTER DELETE ON "content"
+                BEGIN
+                    DELETE FROM "alert" WHERE "content_key" = OLD."key";
+                END"#,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(r#"DROP TRIGGER IF EXISTS "delete_alerts_with_content""#)
+            .await?;
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_alert_content_key")
+                    .table(Alert::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Alert {
+    Table,
+    ContentKey,
+}
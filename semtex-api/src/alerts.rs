@@ -0,0 +1,241 @@
+//! Standing queries: saved searches with alerts on are compared with every
+//! newly indexed page, and pages similar enough to one are recorded as
+//! alerts.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use entity::{alert, content, saved_search};
+use sea_orm::sea_query;
+use sea_orm::{
+    ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, Select,
+};
+use serde::Serialize;
+
+use crate::config::AlertConfig;
+use crate::live::LiveIndex;
+use crate::query::Query;
+use crate::store::cosine_distance;
+use crate::util;
+
+/// Embeddings of the saved queries with alerts on, by model and query text,
+/// kept by the indexer so that a saved query is embedded only once.
+#[derive(Default)]
+pub struct QueryVectors(HashMap<(String, String), Vec<f32>>);
+
+impl QueryVectors {
+    fn get(&mut self, live: &LiveIndex, model: &str, text: &str) -> Vec<f32> {
+        self.0
+            .entry((model.to_string(), text.to_string()))
+            .or_insert_with(|| live.query_vector(text))
+            .clone()
+    }
+}
+
+/// Compares the newly indexed `entries` with the saved searches that have
+/// alerts on and records those that match, returning how many. A saved query
+/// of operators alone matches every page its operators allow.
+pub async fn record_matches(
+    db: &DatabaseConnection,
+    config: &AlertConfig,
+    live: &LiveIndex,
+    vectors: &mut QueryVectors,
+    entries: &[(u64, Vec<f32>)],
+) -> usize {
+    let searches = saved_search::Entity::find()
+        .filter(saved_search::Column::Alert.eq(true))
+        .all(db)
+        .await
+        .unwrap();
+    let model = live.metadata().model;
+
+    let mut matches = Vec::new();
+    let mut used = HashSet::new();
+    for search in searches.iter() {
+        let query = Query::parse(&search.query, Utc::now());
+        let threshold = search.alert_threshold.unwrap_or(config.threshold);
+        let vector = (!query.text.is_empty()).then(|| {
+            used.insert((model.clone(), query.text.clone()));
+            vectors.get(live, &model, &query.text)
+        });
+
+        let mut matching = entries
+            .iter()
+            .map(|(key, document)| {
+                let similarity = match &vector {
+                    Some(vector) => 1.0 - cosine_distance(vector, document),
+                    None => 1.0,
+                };
+                (*key, similarity)
+            })
+            .filter(|(_, similarity)| *similarity >= threshold)
+            .collect::<Vec<_>>();
+
+        // Operators are checked only for the pages that are similar enough.
+        if query.has_filters() && !matching.is_empty() {
+            let keys = matching
+                .iter()
+                .map(|(key, _)| *key as i64)
+                .collect::<Vec<_>>();
+            let allowed = content::Entity::find()
+                .select_only()
+                .column(content::Column::Key)
+                .filter(
+                    crate::content_filter(db, &query)
+                        .await
+                        .add(content::Column::Key.is_in(keys)),
+                )
+                .into_tuple::<i64>()
+                .all(db)
+                .await
+                .unwrap()
+                .into_iter()
+                .collect::<HashSet<_>>();
+            matching.retain(|(key, _)| allowed.contains(&(*key as i64)));
+        }
+        matches.extend(
+            matching
+                .into_iter()
+                .map(|(key, similarity)| (search.id, key, similarity)),
+        );
+    }
+
+    // Searches no longer saved, or saved with another query, are forgotten.
+    vectors.0.retain(|key, _| used.contains(key));
+
+    if matches.is_empty() {
+        return 0;
+    }
+    let created_at = Utc::now().to_rfc3339();
+    let records = matches
+        .iter()
+        .map(|(search_id, key, similarity)| alert::ActiveModel {
+            id: ActiveValue::NotSet,
+            saved_search_id: ActiveValue::Set(*search_id),
+            content_key: ActiveValue::Set(*key as i64),
+            similarity: ActiveValue::Set(*similarity),
+            created_at: ActiveValue::Set(created_at.clone()),
+            seen: ActiveValue::Set(false),
+        })
+        .collect::<Vec<_>>();
+    alert::Entity::insert_many(records).exec(db).await.unwrap();
+    matches.len()
+}
+
+/// A page that matched a saved search when it was indexed.
+#[derive(Debug, Serialize)]
+pub struct Alert {
+    pub id: i32,
+    pub saved_search_id: i32,
+    /// The name and query of the saved search.
+    pub name: String,
+    pub query: String,
+    #[serde(serialize_with = "util::serialize_display")]
+    pub key: u64,
+    pub title: String,
+    pub url: Option<String>,
+    pub similarity: f32,
+    pub created_at: String,
+    pub seen: bool,
+}
+
+/// Alerts whose page and saved search are still there, only those not yet
+/// seen if `unseen`.
+fn select(unseen: bool) -> Select<alert::Entity> {
+    let pages = sea_query::Query::select()
+        .column(content::Column::Key)
+        .from(content::Entity)
+        .to_owned();
+    let searches = sea_query::Query::select()
+        .column(saved_search::Column::Id)
+        .from(saved_search::Entity)
+        .to_owned();
+    let mut select = alert::Entity::find()
+        .filter(alert::Column::ContentKey.in_subquery(pages))
+        .filter(alert::Column::SavedSearchId.in_subquery(searches));
+    if unseen {
+        select = select.filter(alert::Column::Seen.eq(false));
+    }
+    select
+}
+
+/// How many alerts have not been seen yet.
+pub async fn count_unseen(db: &DatabaseConnection) -> u64 {
+    select(true).count(db).await.unwrap()
+}
+
+/// Alerts newest first, only those not yet seen if `unseen`.
+pub async fn list(
+    db: &DatabaseConnection,
+    unseen: bool,
+    offset: usize,
+    limit: usize,
+) -> Vec<Alert> {
+    let alerts = select(unseen)
+        .order_by_desc(alert::Column::Id)
+        .offset(offset as u64)
+        .limit(limit as u64)
+        .all(db)
+        .await
+        .unwrap();
+
+    let keys = alerts.iter().map(|a| a.content_key).collect::<Vec<_>>();
+    let pages = content::Entity::find()
+        .select_only()
+        .column(content::Column::Key)
+        .column(content::Column::Title)
+        .column(content::Column::Url)
+        .filter(content::Column::Key.is_in(keys))
+        .into_tuple::<(i64, String, Option<String>)>()
+        .all(db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(key, title, url)| (key, (title, url)))
+        .collect::<HashMap<_, _>>();
+    let ids = alerts.iter().map(|a| a.saved_search_id).collect::<Vec<_>>();
+    let searches = saved_search::Entity::find()
+        .filter(saved_search::Column::Id.is_in(ids))
+        .all(db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|s| (s.id, s))
+        .collect::<HashMap<_, _>>();
+
+    alerts
+        .into_iter()
+        .filter_map(|a| {
+            let (title, url) = pages.get(&a.content_key)?.clone();
+            let search = searches.get(&a.saved_search_id)?;
+            Some(Alert {
+                id: a.id,
+                saved_search_id: a.saved_search_id,
+                name: search.name.clone(),
+                query: search.query.clone(),
+                key: a.content_key as u64,
+                title,
+                url,
+                similarity: a.similarity,
+                created_at: a.created_at,
+                seen: a.seen,
+            })
+        })
+        .collect()
+}
+
+/// Marks the alerts `ids`, or all of them, as seen, returning how many
+/// changed.
+pub async fn mark_seen(db: &DatabaseConnection, ids: Option<&[i32]>) -> u64 {
+    let mut update = alert::Entity::update_many()
+        .set(alert::ActiveModel {
+            seen: ActiveValue::Set(true),
+            ..Default::default()
+        })
+        .filter(alert::Column::Seen.eq(false));
+    if let Some(ids) = ids {
+        update = update.filter(alert::Column::Id.is_in(ids.to_vec()));
+    }
+    update.exec(db).await.unwrap().rows_affected
+}
@@ -0,0 +1,61 @@
+//! Measures retrieval quality on a labelled dataset, once per configuration:
+//!
+//!     cargo run --release -p semtex-api --bin eval -- eval/sample.jsonl [config.toml ...]
+//!
+//! Without config files the default configuration is evaluated. Reports are
+//! printed to stdout as JSON, one entry per configuration, so runs can be
+//! saved and diffed; a summary table goes to stderr.
+
+use std::path::Path;
+
+use env_logger::Env;
+use semtex_api::config::{read_config, Config};
+use semtex_api::eval::{evaluate, read_dataset};
+
+#[actix_web::main]
+async fn main() {
+    env_logger::init_from_env(Env::default().default_filter_or("warn"));
+
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let Some((dataset, configs)) = args.split_first() else {
+        eprintln!("usage: eval <dataset.jsonl> [config.toml ...]");
+        std::process::exit(2);
+    };
+
+    let dataset = read_dataset(Path::new(dataset));
+    let configs = if configs.is_empty() {
+        vec![("default".to_string(), Config::default())]
+    } else {
+        configs
+            .iter()
+            .map(|path| {
+                let config = read_config(Path::new(path)).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                });
+                (path.clone(), config)
+            })
+            .collect()
+    };
+
+    let mut reports = Vec::new();
+    for (name, config) in configs.iter() {
+        let report = evaluate(name, config, &dataset).await;
+        eprintln!(
+            "{:<30} {:<45} {:?}/{:?}  recall@1 {:.3}  recall@5 {:.3}  recall@10 {:.3}  mrr {:.3}  ndcg@10 {:.3}  {:.2} ms/query",
+            report.name,
+            report.model,
+            report.index.backend,
+            report.index.quantization,
+            report.scores.recall_1,
+            report.scores.recall_5,
+            report.scores.recall_10,
+            report.scores.mrr,
+            report.scores.ndcg_10,
+            report.query_ms
+        );
+        reports.push(report);
+    }
+
+    println!("{}", serde_json::to_string_pretty(&reports).unwrap());
+}
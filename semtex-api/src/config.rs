@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use semtex_vector::cross_encoder::CrossEncoder;
+use semtex_vector::long_text::{LongTextOptions, WindowPooling};
+use semtex_vector::minilm::MiniLM;
+use serde::{Deserialize, Serialize};
+
+use crate::store::{Backend, VectorQuantization};
+use crate::util::xdg_dirs;
+
+/// Settings read from `$XDG_CONFIG_HOME/semtex/config.toml`. Missing keys fall
+/// back to the defaults below.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Hub id of the embedding model used for both indexing and search.
+    pub model: String,
+    /// Threads computing embeddings, shared by search and ingest.
+    pub embedding_workers: usize,
+    /// Threads serving searches. They read the index in parallel, also while
+    /// an import is writing to it.
+    pub search_workers: usize,
+    /// When set, documents are embedded as pooled overlapping windows rather
+    /// than truncated to the model's input length.
+    pub long_text: Option<LongTextConfig>,
+    /// When set, the nearest results are re-scored with a cross-encoder.
+    pub rerank: Option<RerankConfig>,
+    /// When set, results are reordered to trade relevance for variety.
+    pub diversify: Option<DiversifyConfig>,
+    /// When set, results are ranked by a blend of similarity, recency, visits
+    /// and source instead of by similarity alone.
+    pub ranking: Option<RankingConfig>,
+    pub snippets: SnippetConfig,
+    pub query_cache: QueryCacheConfig,
+    pub alerts: AlertConfig,
+    pub index: IndexConfig,
+    /// Where the database and index live, `$XDG_DATA_HOME/semtex` by default.
+    pub data_dir: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            model: MiniLM::MODEL_ID.to_string(),
+            embedding_workers: 2,
+            search_workers: 4,
+            long_text: None,
+            rerank: None,
+            diversify: None,
+            ranking: None,
+            snippets: SnippetConfig::default(),
+            query_cache: QueryCacheConfig::default(),
+            alerts: AlertConfig::default(),
+            index: IndexConfig::default(),
+            data_dir: xdg_dirs().get_data_home(),
+        }
+    }
+}
+
+impl Config {
+    /// Path of `name` inside the data directory, which is created if needed.
+    pub fn data_path(&self, name: &str) -> String {
+        std::fs::create_dir_all(&self.data_dir).unwrap();
+        self.data_dir
+            .join(name)
+            .into_os_string()
+            .into_string()
+            .unwrap()
+    }
+}
+
+/// How vectors are stored and searched. Changing the backend, quantization or
+/// HNSW parameters other than `expansion_search` rebuilds the index in the
+/// background.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct IndexConfig {
+    pub backend: Backend,
+    pub quantization: VectorQuantization,
+    /// HNSW graph degree. Zero uses the usearch default.
+    pub connectivity: usize,
+    /// Candidate list size while inserting. Zero uses the usearch default.
+    pub expansion_add: usize,
+    /// Candidate list size while searching, unless a query overrides it.
+    /// Zero uses the usearch default.
+    pub expansion_search: usize,
+    /// Hours between background compactions, which rebuild the index without
+    /// the space left by removed vectors. Zero only compacts on `POST /compact`.
+    pub compaction_interval_hours: u64,
+    /// Memory-map the usearch index for search instead of loading it. New
+    /// vectors are kept in memory until merged into the mapped index.
+    pub memory_mapped: bool,
+    /// Vectors added or removed before a memory-mapped index is merged.
+    /// Zero uses 10000.
+    pub merge_threshold: usize,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pooling {
+    Mean,
+    Title,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct LongTextConfig {
+    pub window: usize,
+    pub overlap: usize,
+    pub max_windows: usize,
+    pub pooling: Pooling,
+    /// Weight of the title embedding when `pooling = "title"`.
+    pub title_weight: f32,
+}
+
+impl Default for LongTextConfig {
+    fn default() -> Self {
+        let options = LongTextOptions::default();
+        LongTextConfig {
+            window: options.window,
+            overlap: options.overlap,
+            max_windows: options.max_windows,
+            pooling: Pooling::Mean,
+            title_weight: 0.3,
+        }
+    }
+}
+
+impl LongTextConfig {
+    pub fn options(&self) -> LongTextOptions {
+        LongTextOptions {
+            window: self.window,
+            overlap: self.overlap,
+            max_windows: self.max_windows,
+            pooling: match self.pooling {
+                Pooling::Mean => WindowPooling::Mean,
+                Pooling::Title => WindowPooling::Title(self.title_weight),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RerankConfig {
+    /// Hub id of the cross-encoder.
+    pub model: String,
+    /// How many nearest neighbours are re-scored.
+    pub candidates: usize,
+    /// Time the rerank stage may take per search. Candidates not scored in
+    /// time keep their order after the scored ones.
+    pub budget_ms: u64,
+    /// Whether searches are reranked unless they ask not to be.
+    pub by_default: bool,
+}
+
+impl Default for RerankConfig {
+    fn default() -> Self {
+        RerankConfig {
+            model: CrossEncoder::MS_MARCO_MINILM.to_string(),
+            candidates: 20,
+            budget_ms: 300,
+            by_default: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct DiversifyConfig {
+    /// Weight of relevance against unlikeness to results ranked above, from
+    /// 0 to 1. At 1 the order is unchanged.
+    pub lambda: f32,
+    /// How many nearest neighbours the results are chosen from.
+    pub candidates: usize,
+    /// Results from one domain at most. Zero does not limit them.
+    pub max_per_domain: usize,
+    /// Whether searches are diversified unless they ask not to be.
+    pub by_default: bool,
+}
+
+impl Default for DiversifyConfig {
+    fn default() -> Self {
+        DiversifyConfig {
+            lambda: 0.7,
+            candidates: 50,
+            max_per_domain: 0,
+            by_default: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RankingConfig {
+    /// Weight of similarity to the query, which is scaled from 0 to 1.
+    pub similarity_weight: f32,
+    /// Weight of recency, which is 1 for a page visited just now.
+    pub recency_weight: f32,
+    /// Days after which recency halves. Zero ignores recency.
+    pub half_life_days: f32,
+    /// Weight of `ln(1 + visits)`.
+    pub visits_weight: f32,
+    /// Score multiplier per source name; sources not listed have 1.
+    pub source_weights: HashMap<String, f32>,
+    /// How many nearest neighbours are ranked. Results past them follow
+    /// unscored, nearest first.
+    pub candidates: usize,
+}
+
+impl Default for RankingConfig {
+    fn default() -> Self {
+        RankingConfig {
+            similarity_weight: 1.0,
+            recency_weight: 0.2,
+            half_life_days: 30.0,
+            visits_weight: 0.1,
+            source_weights: HashMap::new(),
+            candidates: 50,
+        }
+    }
+}
+
+/// How search results are summarised.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SnippetConfig {
+    /// Passages returned per result.
+    pub passages: usize,
+    /// Whether passages are also compared with the query by embedding, not
+    /// only by the query terms they contain. Costs a few model passes per
+    /// result on every search.
+    pub semantic: bool,
+}
+
+impl Default for SnippetConfig {
+    fn default() -> Self {
+        SnippetConfig {
+            passages: 2,
+            semantic: false,
+        }
+    }
+}
+
+/// The cache of query embeddings.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct QueryCacheConfig {
+    /// Query vectors kept, least recently used first out; 0 disables the
+    /// cache.
+    pub capacity: usize,
+    /// Whether the cache is saved in the data directory, to outlive restarts.
+    pub persist: bool,
+}
+
+impl Default for QueryCacheConfig {
+    fn default() -> Self {
+        QueryCacheConfig {
+            capacity: 1024,
+            persist: false,
+        }
+    }
+}
+
+/// Alerts raised when newly indexed pages match saved searches.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct AlertConfig {
+    /// Cosine similarity to a saved query a page needs to raise an alert,
+    /// unless the saved search sets its own.
+    pub threshold: f32,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        AlertConfig { threshold: 0.5 }
+    }
+}
+
+pub fn load_config() -> Result<Config, String> {
+    match xdg_dirs().find_config_file("config.toml") {
+        Some(path) => read_config(&path),
+        None => Ok(Config::default()),
+    }
+}
+
+/// Reads the config at `path`, or reports why it cannot be read.
+pub fn read_config(path: &Path) -> Result<Config, String> {
+    let config = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    toml::from_str(&config).map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,69 @@
+//! Maximal marginal relevance: reorders results so that each next one is
+//! relevant to the query but unlike those already chosen, so mirrors and
+//! syndicated copies of a page do not fill the first page of results.
+
+use std::collections::HashMap;
+
+use crate::store::cosine_distance;
+
+pub struct Candidate<'a> {
+    /// How well the candidate matches the query, higher is better.
+    pub relevance: f32,
+    pub vector: &'a [f32],
+    /// The site the candidate comes from, if known.
+    pub domain: Option<String>,
+}
+
+/// Greedy MMR over `candidates`, returning their indices in the chosen
+/// order. Each step takes the candidate maximising
+/// `lambda * relevance - (1 - lambda) * similarity to the closest chosen`,
+/// so `lambda = 1` keeps the relevance order and lower values favour
+/// variety. Candidates from a domain that already has `max_per_domain`
+/// results are left out.
+pub fn mmr(candidates: &[Candidate], lambda: f32, max_per_domain: Option<usize>) -> Vec<usize> {
+    let mut chosen = Vec::<usize>::new();
+    let mut remaining = (0..candidates.len()).collect::<Vec<_>>();
+    // Similarity of each candidate to the closest chosen one.
+    let mut redundancy = vec![0.0f32; candidates.len()];
+    let mut per_domain = HashMap::<&str, usize>::new();
+
+    loop {
+        remaining.retain(|&i| match (&candidates[i].domain, max_per_domain) {
+            (Some(domain), Some(max)) => {
+                per_domain.get(domain.as_str()).copied().unwrap_or(0) < max
+            }
+            _ => true,
+        });
+
+        let score = |i: usize| lambda * candidates[i].relevance - (1.0 - lambda) * redundancy[i];
+        let Some(position) = (0..remaining.len()).max_by(|&a, &b| {
+            score(remaining[a])
+                .total_cmp(&score(remaining[b]))
+                .then(b.cmp(&a))
+        }) else {
+            return chosen;
+        };
+
+        let next = remaining.remove(position);
+        chosen.push(next);
+        if let Some(domain) = &candidates[next].domain {
+            *per_domain.entry(domain).or_insert(0) += 1;
+        }
+        for &i in remaining.iter() {
+            let similarity = 1.0 - cosine_distance(candidates[i].vector, candidates[next].vector);
+            redundancy[i] = redundancy[i].max(similarity);
+        }
+    }
+}
+
+/// The host of `url`, lower-cased and without a leading `www.`.
+pub fn domain(url: &str) -> Option<String> {
+    let rest = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+    if host.is_empty() {
+        return None;
+    }
+    let host = host.to_lowercase();
+    Some(host.strip_prefix("www.").unwrap_or(&host).to_string())
+}
@@ -0,0 +1,299 @@
+//! `semtex-api doctor [--repair]`: checks that the index and the `content`
+//! table agree, and optionally fixes them. Run it while the server is stopped,
+//! since both write the index.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+use entity::content;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect};
+
+use crate::config::Config;
+use crate::connect;
+use crate::embedding::EmbeddingService;
+use crate::index::{
+    load_metadata, save_metadata, store_path, DocumentKeys, IndexMetadata, INDEX_NAME,
+};
+use crate::store::{
+    discard_delta, has_delta, open_store, try_open_store, try_view_store, VectorStore,
+};
+use crate::vectors::record_vectors;
+
+const REPAIR_BATCH_SIZE: usize = 32;
+
+#[derive(Debug, Default)]
+pub struct Diagnosis {
+    pub rows: usize,
+    pub vectors: usize,
+    /// Keys of content rows without a vector, which search can never return.
+    pub unindexed: Vec<u64>,
+    /// Vectors whose content row no longer exists.
+    pub orphans: Vec<u64>,
+    /// Vectors the index holds but could not enumerate, so they cannot be
+    /// checked against the database.
+    pub unreachable: usize,
+    /// Why the index cannot be used at all, e.g. mismatched dimensions.
+    pub broken: Option<String>,
+}
+
+impl Diagnosis {
+    pub fn is_healthy(&self) -> bool {
+        self.unindexed.is_empty()
+            && self.orphans.is_empty()
+            && self.unreachable == 0
+            && self.broken.is_none()
+    }
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "content rows:      {}", self.rows)?;
+        writeln!(f, "indexed vectors:   {}", self.vectors)?;
+        writeln!(f, "unindexed rows:    {}", self.unindexed.len())?;
+        writeln!(f, "orphan vectors:    {}", self.orphans.len())?;
+        writeln!(f, "unreachable:       {}", self.unreachable)?;
+        if let Some(broken) = &self.broken {
+            writeln!(f, "broken index:      {}", broken)?;
+        }
+        write!(
+            f,
+            "{}",
+            if self.is_healthy() { "ok" } else { "inconsistent" }
+        )
+    }
+}
+
+struct State {
+    metadata: IndexMetadata,
+    db: DatabaseConnection,
+    embeddings: EmbeddingService,
+    keys: Vec<u64>,
+    store: Result<Box<dyn VectorStore>, String>,
+}
+
+async fn inspect(config: &Config) -> State {
+    let db = connect(config).await;
+    let keys = content::Entity::find()
+        .select_only()
+        .column(content::Column::Key)
+        .into_tuple::<i64>()
+        .all(&db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|key| key as u64)
+        .collect::<Vec<_>>();
+
+    let metadata = load_metadata(config);
+    let model = match &metadata {
+        Some(metadata) => metadata.model.clone(),
+        None => config.model.clone(),
+    };
+    let embeddings = EmbeddingService::start(&model, config);
+
+    let (metadata, store) = match metadata {
+        Some(metadata) if metadata.dimensions != embeddings.dimensions() => {
+            let broken = format!(
+                "index.json records {} dimensions but {} produces {}",
+                metadata.dimensions,
+                model,
+                embeddings.dimensions()
+            );
+            (metadata, Err(broken))
+        }
+        Some(metadata) if metadata.keys == DocumentKeys::RowId => {
+            let broken = "vectors are keyed by content row id".to_string();
+            (metadata, Err(broken))
+        }
+        Some(metadata) => {
+            let path = store_path(config, &metadata, INDEX_NAME);
+            let store = if !Path::new(&path).exists() {
+                Err(format!("{} is missing", path))
+            } else if config.index.memory_mapped || has_delta(&path) {
+                try_view_store(&metadata, &path)
+            } else {
+                try_open_store(&metadata, &path)
+            };
+            (metadata, store)
+        }
+        None => {
+            let metadata =
+                IndexMetadata::new(&model, embeddings.dimensions(), &config.index);
+            let store = if keys.is_empty() {
+                Ok(open_store(&metadata, &store_path(config, &metadata, INDEX_NAME)))
+            } else {
+                Err("index.json is missing".to_string())
+            };
+            (metadata, store)
+        }
+    };
+
+    State {
+        metadata,
+        db,
+        embeddings,
+        keys,
+        store,
+    }
+}
+
+fn diagnose_state(state: &State) -> Diagnosis {
+    let store = match &state.store {
+        Ok(store) => store,
+        Err(broken) => {
+            return Diagnosis {
+                rows: state.keys.len(),
+                unindexed: state.keys.clone(),
+                broken: Some(broken.clone()),
+                ..Diagnosis::default()
+            }
+        }
+    };
+
+    let rows = state.keys.iter().copied().collect::<HashSet<_>>();
+    let keys = store.keys();
+    let vectors = store.stats().size;
+
+    Diagnosis {
+        rows: state.keys.len(),
+        vectors,
+        unindexed: state
+            .keys
+            .iter()
+            .copied()
+            .filter(|key| !store.contains(*key))
+            .collect(),
+        orphans: keys
+            .iter()
+            .copied()
+            .filter(|key| !rows.contains(key))
+            .collect(),
+        unreachable: vectors.saturating_sub(keys.len()),
+        broken: None,
+    }
+}
+
+pub async fn diagnose(config: &Config) -> Diagnosis {
+    diagnose_state(&inspect(config).await)
+}
+
+/// Adds the content rows with `keys` to `store`, using their stored vectors or
+/// `fallback` where possible and embedding the rest.
+async fn index_rows(
+    db: &DatabaseConnection,
+    model: &str,
+    embeddings: &EmbeddingService,
+    store: &mut dyn VectorStore,
+    keys: &[u64],
+    fallback: impl Fn(u64) -> Option<Vec<f32>>,
+) {
+    for batch in keys.chunks(REPAIR_BATCH_SIZE) {
+        let records = content::Entity::find()
+            .filter(content::Column::Key.is_in(batch.iter().map(|key| *key as i64)))
+            .all(db)
+            .await
+            .unwrap();
+
+        for (key, vector) in record_vectors(db, model, embeddings, &records, &fallback).await {
+            store.add(key, &vector);
+        }
+    }
+}
+
+/// Fixes whatever `diagnose` finds and returns the diagnosis from before the
+/// repair. Unindexed rows are indexed and orphan vectors removed. A broken
+/// index, or one with vectors that cannot be enumerated, is rebuilt from the
+/// database. Rows are only re-embedded if neither the database nor the old
+/// index has their vector.
+pub async fn repair(config: &Config) -> Diagnosis {
+    let state = inspect(config).await;
+    let diagnosis = diagnose_state(&state);
+    if diagnosis.is_healthy() {
+        return diagnosis;
+    }
+
+    let State {
+        metadata,
+        db,
+        embeddings,
+        keys,
+        store,
+    } = state;
+    let rebuild = diagnosis.broken.is_some() || diagnosis.unreachable > 0;
+
+    let (metadata, store) = match store {
+        Ok(mut store) if !rebuild => {
+            for key in diagnosis.orphans.iter() {
+                store.remove(*key);
+            }
+            index_rows(
+                &db,
+                &metadata.model,
+                &embeddings,
+                store.as_mut(),
+                &diagnosis.unindexed,
+                |_| None,
+            )
+            .await;
+            (metadata, store)
+        }
+        old => {
+            let metadata = IndexMetadata {
+                dimensions: embeddings.dimensions(),
+                keys: DocumentKeys::Stable,
+                ..metadata
+            };
+            let rebuild_path = store_path(config, &metadata, "index.repair");
+            let _ = std::fs::remove_file(&rebuild_path);
+            let mut rebuilt = open_store(&metadata, &rebuild_path);
+            let _ = std::fs::remove_file(&rebuild_path);
+
+            let old = old.ok();
+            index_rows(
+                &db,
+                &metadata.model,
+                &embeddings,
+                rebuilt.as_mut(),
+                &keys,
+                |key| old.as_ref().and_then(|old| old.get(key)),
+            )
+            .await;
+            (metadata, rebuilt)
+        }
+    };
+
+    let path = store_path(config, &metadata, INDEX_NAME);
+    store.persist(&path);
+    if rebuild {
+        // A rebuilt memory-mapped index already includes its delta.
+        discard_delta(&path);
+    }
+    save_metadata(config, &metadata);
+    diagnosis
+}
+
+/// Entry point of the `doctor` subcommand. Exits non-zero if problems were
+/// found and not repaired.
+pub async fn run(repair_problems: bool) {
+    let config = crate::config::load_config().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+
+    let diagnosis = if repair_problems {
+        repair(&config).await
+    } else {
+        diagnose(&config).await
+    };
+    println!("{}", diagnosis);
+
+    if !diagnosis.is_healthy() {
+        if repair_problems {
+            println!("repaired");
+        } else {
+            println!("run `semtex-api doctor --repair` to fix");
+            std::process::exit(1);
+        }
+    }
+}
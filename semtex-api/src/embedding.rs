@@ -0,0 +1,209 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use semtex_vector::long_text::LongText;
+use semtex_vector::{load_model, Embedder};
+
+use crate::config::Config;
+
+/// Embeds documents for the index, pooling over windows in long-text mode.
+pub struct DocumentEmbedder {
+    model: Box<dyn Embedder>,
+    long_text: Option<LongText>,
+}
+
+impl DocumentEmbedder {
+    pub fn new(model_id: &str, config: &Config) -> Result<DocumentEmbedder, String> {
+        let long_text = match &config.long_text {
+            Some(long_text) => Some(
+                LongText::new(model_id, long_text.options())
+                    .map_err(|e| format!("cannot load the tokenizer of `{}`: {}", model_id, e))?,
+            ),
+            None => None,
+        };
+        Ok(DocumentEmbedder {
+            model: load_model(model_id)?,
+            long_text,
+        })
+    }
+
+    pub fn embed_document(&self, title: &str, text: &str) -> Vec<f32> {
+        match &self.long_text {
+            Some(long_text) => long_text.embed_document(self.model.as_ref(), title, text),
+            None => self.model.embed_documents(&[text]).remove(0),
+        }
+    }
+}
+
+enum Job {
+    Query {
+        query: String,
+        result: Sender<Vec<f32>>,
+    },
+    Document {
+        title: String,
+        text: String,
+        result: Sender<Vec<f32>>,
+    },
+    /// Short passages embedded as documents for a waiting search, so they
+    /// go ahead of queued documents.
+    Passages {
+        passages: Vec<String>,
+        result: Sender<Vec<Vec<f32>>>,
+    },
+}
+
+#[derive(Default)]
+struct Queue {
+    queries: VecDeque<Job>,
+    documents: VecDeque<Job>,
+    shutdown: bool,
+}
+
+struct Shared {
+    embedder: DocumentEmbedder,
+    queue: Mutex<Queue>,
+    ready: Condvar,
+}
+
+/// Stops the workers once the last `EmbeddingService` handle is dropped.
+struct Workers(Arc<Shared>);
+
+impl Drop for Workers {
+    fn drop(&mut self) {
+        self.0.queue.lock().unwrap().shutdown = true;
+        self.0.ready.notify_all();
+    }
+}
+
+/// One embedding model shared by the searcher and indexer, served by a pool of
+/// worker threads. Queries are always taken before queued documents, so
+/// searches stay responsive during a large import.
+#[derive(Clone)]
+pub struct EmbeddingService {
+    shared: Arc<Shared>,
+    _workers: Arc<Workers>,
+}
+
+impl EmbeddingService {
+    pub fn start(model_id: &str, config: &Config) -> EmbeddingService {
+        EmbeddingService::try_start(model_id, config).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like `start`, but reports an unknown model or one that cannot be
+    /// loaded.
+    pub fn try_start(model_id: &str, config: &Config) -> Result<EmbeddingService, String> {
+        let shared = Arc::new(Shared {
+            embedder: DocumentEmbedder::new(model_id, config)?,
+            queue: Mutex::new(Queue::default()),
+            ready: Condvar::new(),
+        });
+
+        for i in 0..config.embedding_workers.max(1) {
+            let shared = shared.clone();
+            thread::Builder::new()
+                .name(format!("embedding-{}", i))
+                .spawn(move || work(&shared))
+                .unwrap();
+        }
+
+        Ok(EmbeddingService {
+            _workers: Arc::new(Workers(shared.clone())),
+            shared,
+        })
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.shared.embedder.model.dimensions()
+    }
+
+    pub fn embed_query(&self, query: &str) -> Vec<f32> {
+        let (result, receiver) = channel();
+        self.push(Job::Query {
+            query: query.to_string(),
+            result,
+        });
+        receiver.recv().unwrap()
+    }
+
+    /// Embeds `(title, text)` pairs, spread across the workers.
+    pub fn embed_documents(&self, documents: &[(&str, &str)]) -> Vec<Vec<f32>> {
+        let receivers = documents
+            .iter()
+            .map(|(title, text)| {
+                let (result, receiver) = channel();
+                self.push(Job::Document {
+                    title: title.to_string(),
+                    text: text.to_string(),
+                    result,
+                });
+                receiver
+            })
+            .collect::<Vec<_>>();
+
+        receivers
+            .iter()
+            .map(|receiver| receiver.recv().unwrap())
+            .collect::<Vec<_>>()
+    }
+
+    /// Embeds short passages, such as sentences, in one batch and ahead of
+    /// queued documents. Unlike documents they are never split into windows.
+    pub fn embed_passages(&self, passages: &[&str]) -> Vec<Vec<f32>> {
+        if passages.is_empty() {
+            return Vec::new();
+        }
+        let (result, receiver) = channel();
+        self.push(Job::Passages {
+            passages: passages.iter().map(|p| p.to_string()).collect(),
+            result,
+        });
+        receiver.recv().unwrap()
+    }
+
+    fn push(&self, job: Job) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        match job {
+            Job::Query { .. } | Job::Passages { .. } => queue.queries.push_back(job),
+            Job::Document { .. } => queue.documents.push_back(job),
+        }
+        self.shared.ready.notify_one();
+    }
+}
+
+fn work(shared: &Shared) {
+    loop {
+        let job = {
+            let mut queue = shared.queue.lock().unwrap();
+            loop {
+                if queue.shutdown {
+                    return;
+                }
+                if let Some(job) = queue.queries.pop_front().or_else(|| queue.documents.pop_front()) {
+                    break job;
+                }
+                queue = shared.ready.wait(queue).unwrap();
+            }
+        };
+
+        // The requester may have gone away; its result is then discarded.
+        match job {
+            Job::Query { query, result } => {
+                let _ = result.send(shared.embedder.model.embed_query(&query));
+            }
+            Job::Document {
+                title,
+                text,
+                result,
+            } => {
+                let _ = result.send(shared.embedder.embed_document(&title, &text));
+            }
+            Job::Passages { passages, result } => {
+                let passages = passages.iter().map(|p| p.as_str()).collect::<Vec<_>>();
+                let _ = result.send(shared.embedder.model.embed_documents(&passages));
+            }
+        }
+    }
+}
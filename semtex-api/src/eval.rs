@@ -0,0 +1,229 @@
+//! Retrieval-quality evaluation against a labelled dataset.
+//!
+//! A dataset is a JSONL file mixing documents and queries:
+//!
+//! ```text
+//! {"id": "bread", "title": "Sourdough", "content": "Sourdough bread relies on..."}
+//! {"query": "baking bread with a starter", "relevant": ["bread"]}
+//! ```
+//!
+//! The documents are ingested into a fresh instance in a temporary directory
+//! and every query goes through the same search path as `/search`.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use tempfile::TempDir;
+
+use crate::config::{Config, IndexConfig};
+use crate::{ingest_items, search_content, start, IngestItem, SearchParams, Source};
+
+const INGEST_BATCH_SIZE: usize = 64;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Record {
+    Document {
+        id: String,
+        #[serde(default)]
+        title: String,
+        content: String,
+    },
+    Query {
+        query: String,
+        relevant: Vec<String>,
+    },
+}
+
+pub struct Document {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+}
+
+pub struct Query {
+    pub query: String,
+    pub relevant: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct Dataset {
+    pub documents: Vec<Document>,
+    pub queries: Vec<Query>,
+}
+
+pub fn read_dataset(path: &Path) -> Dataset {
+    let mut dataset = Dataset::default();
+
+    for (i, line) in std::fs::read_to_string(path).unwrap().lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str(line) {
+            Ok(Record::Document { id, title, content }) => {
+                dataset.documents.push(Document { id, title, content })
+            }
+            // Recall and nDCG are undefined without relevant documents.
+            Ok(Record::Query { relevant, .. }) if relevant.is_empty() => {
+                panic!(
+                    "{}:{}: query has no relevant documents",
+                    path.display(),
+                    i + 1
+                )
+            }
+            Ok(Record::Query { query, relevant }) => {
+                dataset.queries.push(Query { query, relevant })
+            }
+            Err(e) => panic!("{}:{}: {}", path.display(), i + 1, e),
+        }
+    }
+
+    dataset
+}
+
+/// Metrics for a single query, given the ranked document ids returned.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Scores {
+    #[serde(rename = "recall@1")]
+    pub recall_1: f64,
+    #[serde(rename = "recall@5")]
+    pub recall_5: f64,
+    #[serde(rename = "recall@10")]
+    pub recall_10: f64,
+    pub mrr: f64,
+    #[serde(rename = "ndcg@10")]
+    pub ndcg_10: f64,
+}
+
+/// Scores one ranking with binary relevance.
+pub fn score(ranked: &[String], relevant: &[String]) -> Scores {
+    let is_relevant = |id: &String| relevant.contains(id);
+    let recall = |k: usize| {
+        ranked.iter().take(k).filter(|id| is_relevant(id)).count() as f64 / relevant.len() as f64
+    };
+
+    let mrr = ranked
+        .iter()
+        .position(is_relevant)
+        .map(|rank| 1.0 / (rank + 1) as f64)
+        .unwrap_or(0.0);
+
+    let gain = |rank: usize| 1.0 / ((rank + 2) as f64).log2();
+    let dcg = ranked
+        .iter()
+        .take(10)
+        .enumerate()
+        .filter(|(_, id)| is_relevant(id))
+        .map(|(rank, _)| gain(rank))
+        .sum::<f64>();
+    let ideal = (0..relevant.len().min(10)).map(gain).sum::<f64>();
+
+    Scores {
+        recall_1: recall(1),
+        recall_5: recall(5),
+        recall_10: recall(10),
+        mrr,
+        ndcg_10: dcg / ideal,
+    }
+}
+
+/// Mean scores of one configuration over a dataset.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub name: String,
+    pub model: String,
+    pub long_text: bool,
+    /// The cross-encoder results were reranked with, if any.
+    pub rerank: Option<String>,
+    /// The MMR lambda results were diversified with, if any.
+    pub diversify: Option<f32>,
+    pub index: IndexConfig,
+    pub documents: usize,
+    pub queries: usize,
+    #[serde(flatten)]
+    pub scores: Scores,
+    /// Mean search latency, including the query embedding. Unlike the
+    /// scores it varies between runs.
+    pub query_ms: f64,
+}
+
+/// Ingests the dataset into a temporary instance built from `config` and runs
+/// every query against it. Must be called from within a running actix system.
+pub async fn evaluate(name: &str, config: &Config, dataset: &Dataset) -> Report {
+    let dir = TempDir::new().unwrap();
+    let config = Config {
+        data_dir: dir.path().to_path_buf(),
+        ..config.clone()
+    };
+    let state = start(&config).await;
+
+    let mut id_by_key = HashMap::new();
+    for documents in dataset.documents.chunks(INGEST_BATCH_SIZE) {
+        let items = documents
+            .iter()
+            .map(|d| IngestItem {
+                title: d.title.clone(),
+                content: d.content.clone(),
+                source: Source {
+                    name: "eval".to_string(),
+                    url: None,
+                },
+            })
+            .collect::<Vec<_>>();
+
+        let keys = ingest_items(&state, &items).await;
+        for (key, document) in keys.into_iter().zip(documents) {
+            id_by_key.insert(key, document.id.clone());
+        }
+    }
+
+    let mut total = Scores::default();
+    let start = Instant::now();
+    for query in dataset.queries.iter() {
+        let ranked = search_content(&state, &SearchParams::new(&query.query))
+            .await
+            .iter()
+            .map(|r| id_by_key[&r.key].clone())
+            .collect::<Vec<_>>();
+
+        let scores = score(&ranked, &query.relevant);
+        total.recall_1 += scores.recall_1;
+        total.recall_5 += scores.recall_5;
+        total.recall_10 += scores.recall_10;
+        total.mrr += scores.mrr;
+        total.ndcg_10 += scores.ndcg_10;
+    }
+
+    let elapsed = start.elapsed();
+
+    let n = dataset.queries.len().max(1) as f64;
+    Report {
+        name: name.to_string(),
+        model: config.model.clone(),
+        long_text: config.long_text.is_some(),
+        rerank: config
+            .rerank
+            .as_ref()
+            .filter(|rerank| rerank.by_default)
+            .map(|rerank| rerank.model.clone()),
+        diversify: config
+            .diversify
+            .as_ref()
+            .filter(|diversify| diversify.by_default)
+            .map(|diversify| diversify.lambda),
+        index: config.index.clone(),
+        documents: dataset.documents.len(),
+        queries: dataset.queries.len(),
+        scores: Scores {
+            recall_1: total.recall_1 / n,
+            recall_5: total.recall_5 / n,
+            recall_10: total.recall_10 / n,
+            mrr: total.mrr / n,
+            ndcg_10: total.ndcg_10 / n,
+        },
+        query_ms: elapsed.as_secs_f64() * 1000.0 / n,
+    }
+}
@@ -0,0 +1,83 @@
+//! What `explain=true` adds to a search, for finding out why a result ranks
+//! where it does or is missing.
+
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// How the searcher embedded and looked up the query.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchTrace {
+    pub embed: Duration,
+    /// The nearest neighbour search, including fetching vectors.
+    pub search: Duration,
+    /// Euclidean norm of the query embedding.
+    pub query_norm: f32,
+}
+
+/// Milliseconds spent in each stage of a search; stages that did not run
+/// are left out.
+#[derive(Debug, Default, Serialize)]
+pub struct Timings {
+    /// Finding the pages the query's operators allow.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<f64>,
+    pub embed: f64,
+    /// The nearest neighbour search.
+    pub search: f64,
+    /// Loading the hits from the database.
+    pub fetch: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rerank: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diversify: Option<f64>,
+    pub snippets: f64,
+    pub total: f64,
+}
+
+/// How many pages one of the query's operators allows on its own.
+#[derive(Debug, Serialize)]
+pub struct FilterDecision {
+    /// The operator, as it would be written in a query.
+    pub operator: String,
+    pub matching: usize,
+}
+
+/// How a search went as a whole.
+#[derive(Debug, Default, Serialize)]
+pub struct Explanation {
+    /// The free text left once operators are taken out, which is embedded.
+    pub text: String,
+    /// Norm of the query embedding; without free text nothing is embedded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding_norm: Option<f32>,
+    pub filters: Vec<FilterDecision>,
+    /// Pages allowed by all operators together, if the query has any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed: Option<usize>,
+    /// Hits fetched from the index, before paging.
+    pub candidates: usize,
+    /// Candidates left out by `max_per_domain`.
+    pub dropped_by_domain: usize,
+    /// Sentences embedded to choose snippets, each a model pass.
+    pub embedded_sentences: usize,
+    pub timings: Timings,
+}
+
+/// How a single result was scored.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct HitExplanation {
+    /// Position among the candidates by vector distance alone, from 0.
+    pub nearest_rank: usize,
+    /// Fraction of the query terms found in the title or text.
+    pub lexical_score: f32,
+    /// The relevance results were finally ordered by, from 0 to 1: the
+    /// scaled ranking or rerank score, or else the cosine similarity.
+    pub fused_score: f32,
+}
+
+pub fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,255 @@
+//! What was searched for and which results were opened, and searches saved
+//! by name to run again.
+
+use chrono::Utc;
+use entity::{alert, saved_search, search_log, setting};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::query::Query;
+use crate::util;
+
+/// The setting that records whether searches are recorded.
+const HISTORY_SETTING: &str = "history.enabled";
+
+/// Whether searches are recorded; they are unless turned off.
+pub async fn load_enabled(db: &DatabaseConnection) -> bool {
+    setting::Entity::find_by_id(HISTORY_SETTING)
+        .one(db)
+        .await
+        .unwrap()
+        .map(|setting| setting.value == "true")
+        .unwrap_or(true)
+}
+
+pub async fn save_enabled(db: &DatabaseConnection, enabled: bool) {
+    let record = setting::ActiveModel {
+        name: ActiveValue::Set(HISTORY_SETTING.to_string()),
+        value: ActiveValue::Set(enabled.to_string()),
+    };
+    setting::Entity::insert(record)
+        .on_conflict(
+            OnConflict::column(setting::Column::Name)
+                .update_column(setting::Column::Value)
+                .to_owned(),
+        )
+        .exec(db)
+        .await
+        .unwrap();
+}
+
+/// A recorded search.
+#[derive(Debug, Serialize)]
+pub struct HistoryEntry {
+    pub id: i32,
+    pub query: String,
+    /// The operators in the query, one by one.
+    pub filters: Vec<String>,
+    pub created_at: String,
+    /// Keys of the results opened, in the order they were opened.
+    pub clicked: Vec<String>,
+}
+
+impl From<search_log::Model> for HistoryEntry {
+    fn from(r: search_log::Model) -> Self {
+        HistoryEntry {
+            id: r.id,
+            query: r.query,
+            filters: serde_json::from_str(&r.filters).unwrap(),
+            created_at: r.created_at,
+            clicked: serde_json::from_str(&r.clicked).unwrap(),
+        }
+    }
+}
+
+/// Records a search for `input`, parsed as `query`, and returns its id.
+pub async fn log_search(db: &DatabaseConnection, input: &str, query: &Query) -> i32 {
+    let filters = query
+        .operators()
+        .into_iter()
+        .map(|(operator, _)| operator)
+        .collect::<Vec<_>>();
+    let record = search_log::ActiveModel {
+        id: ActiveValue::NotSet,
+        query: ActiveValue::Set(input.to_string()),
+        filters: ActiveValue::Set(serde_json::to_string(&filters).unwrap()),
+        created_at: ActiveValue::Set(Utc::now().to_rfc3339()),
+        clicked: ActiveValue::Set("[]".to_string()),
+    };
+    record.insert(db).await.unwrap().id
+}
+
+/// Records that the result stored under `key` was opened from search `id`,
+/// or returns false if there is no such search.
+pub async fn record_click(db: &DatabaseConnection, id: i32, key: u64) -> bool {
+    let Some(record) = search_log::Entity::find_by_id(id).one(db).await.unwrap() else {
+        return false;
+    };
+    let mut clicked: Vec<String> = serde_json::from_str(&record.clicked).unwrap();
+    clicked.push(key.to_string());
+
+    let mut record: search_log::ActiveModel = record.into();
+    record.clicked = ActiveValue::Set(serde_json::to_string(&clicked).unwrap());
+    record.update(db).await.unwrap();
+    true
+}
+
+/// Recorded searches, newest first.
+pub async fn list(db: &DatabaseConnection, offset: usize, limit: usize) -> Vec<HistoryEntry> {
+    search_log::Entity::find()
+        .order_by_desc(search_log::Column::Id)
+        .offset(offset as u64)
+        .limit(limit as u64)
+        .all(db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(HistoryEntry::from)
+        .collect()
+}
+
+/// Deletes the recorded search `id`, or all of them, and returns how many
+/// were deleted.
+pub async fn delete(db: &DatabaseConnection, id: Option<i32>) -> u64 {
+    let result = match id {
+        Some(id) => search_log::Entity::delete_by_id(id).exec(db).await,
+        None => search_log::Entity::delete_many().exec(db).await,
+    };
+    result.unwrap().rows_affected
+}
+
+/// A search saved under a name.
+#[derive(Debug, Serialize)]
+pub struct SavedSearch {
+    pub id: i32,
+    pub name: String,
+    pub query: String,
+    pub pinned: bool,
+    pub created_at: String,
+    /// Whether newly indexed pages matching the search raise alerts.
+    pub alert: bool,
+    /// Overrides `alerts.threshold` for this search.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert_threshold: Option<f32>,
+}
+
+impl From<saved_search::Model> for SavedSearch {
+    fn from(r: saved_search::Model) -> Self {
+        SavedSearch {
+            id: r.id,
+            name: r.name,
+            query: r.query,
+            pinned: r.pinned,
+            created_at: r.created_at,
+            alert: r.alert,
+            alert_threshold: r.alert_threshold,
+        }
+    }
+}
+
+/// A search to save.
+#[derive(Debug, Default, Deserialize)]
+pub struct NewSavedSearch {
+    pub name: String,
+    pub query: String,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub alert: bool,
+    pub alert_threshold: Option<f32>,
+}
+
+/// Changes to a saved search; fields left out stay as they are.
+#[derive(Debug, Default, Deserialize)]
+pub struct SavedSearchChanges {
+    pub name: Option<String>,
+    pub query: Option<String>,
+    pub pinned: Option<bool>,
+    pub alert: Option<bool>,
+    /// A new threshold, or `Some(None)` to go back to `alerts.threshold`.
+    #[serde(default, deserialize_with = "util::deserialize_some")]
+    pub alert_threshold: Option<Option<f32>>,
+}
+
+/// Saved searches, pinned ones first, then by name.
+pub async fn list_saved(db: &DatabaseConnection) -> Vec<SavedSearch> {
+    saved_search::Entity::find()
+        .order_by_desc(saved_search::Column::Pinned)
+        .order_by_asc(saved_search::Column::Name)
+        .all(db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(SavedSearch::from)
+        .collect()
+}
+
+pub async fn get_saved(db: &DatabaseConnection, id: i32) -> Option<SavedSearch> {
+    saved_search::Entity::find_by_id(id)
+        .one(db)
+        .await
+        .unwrap()
+        .map(SavedSearch::from)
+}
+
+pub async fn save_search(db: &DatabaseConnection, new: &NewSavedSearch) -> SavedSearch {
+    let record = saved_search::ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set(new.name.clone()),
+        query: ActiveValue::Set(new.query.clone()),
+        pinned: ActiveValue::Set(new.pinned),
+        created_at: ActiveValue::Set(Utc::now().to_rfc3339()),
+        alert: ActiveValue::Set(new.alert),
+        alert_threshold: ActiveValue::Set(new.alert_threshold),
+    };
+    record.insert(db).await.unwrap().into()
+}
+
+/// Applies `changes` to saved search `id`, or returns `None` if there is no
+/// such search.
+pub async fn update_saved(
+    db: &DatabaseConnection,
+    id: i32,
+    changes: &SavedSearchChanges,
+) -> Option<SavedSearch> {
+    let record = saved_search::Entity::find_by_id(id)
+        .one(db)
+        .await
+        .unwrap()?;
+    let mut record: saved_search::ActiveModel = record.into();
+    if let Some(name) = &changes.name {
+        record.name = ActiveValue::Set(name.clone());
+    }
+    if let Some(query) = &changes.query {
+        record.query = ActiveValue::Set(query.clone());
+    }
+    if let Some(pinned) = changes.pinned {
+        record.pinned = ActiveValue::Set(pinned);
+    }
+    if let Some(alert) = changes.alert {
+        record.alert = ActiveValue::Set(alert);
+    }
+    if let Some(threshold) = changes.alert_threshold {
+        record.alert_threshold = ActiveValue::Set(threshold);
+    }
+    Some(record.update(db).await.unwrap().into())
+}
+
+/// Deletes saved search `id` and its alerts, returning whether there was
+/// one.
+pub async fn delete_saved(db: &DatabaseConnection, id: i32) -> bool {
+    alert::Entity::delete_many()
+        .filter(alert::Column::SavedSearchId.eq(id))
+        .exec(db)
+        .await
+        .unwrap();
+    let result = saved_search::Entity::delete_by_id(id)
+        .exec(db)
+        .await
+        .unwrap();
+    result.rows_affected > 0
+}
@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use semtex_vector::minilm::MiniLM;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, IndexConfig};
+use crate::store::{Backend, VectorQuantization};
+
+/// Name of the live index, stored as `index.<backend extension>`.
+pub const INDEX_NAME: &str = "index";
+/// The index file written before `index.json` existed.
+pub const LEGACY_INDEX_FILE: &str = "index.usearch";
+pub const METADATA_FILE: &str = "index.json";
+
+/// What the vector keys of an index refer to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentKeys {
+    /// The content row id, in indexes built before content had stable keys.
+    #[default]
+    RowId,
+    /// The content `key`.
+    Stable,
+}
+
+/// Content rows a search is restricted to, by both kinds of key, since the
+/// kind the index uses is only known to the searcher.
+#[derive(Clone, Debug, Default)]
+pub struct AllowedDocuments {
+    pub keys: HashSet<u64>,
+    pub ids: HashSet<u64>,
+}
+
+impl AllowedDocuments {
+    pub fn contains(&self, keys: DocumentKeys, key: u64) -> bool {
+        match keys {
+            DocumentKeys::Stable => self.keys.contains(&key),
+            DocumentKeys::RowId => self.ids.contains(&key),
+        }
+    }
+}
+
+/// Describes how the vectors in the index were produced. Stored next to the
+/// index as `index.json` so that an index is never searched with a model it
+/// was not built with.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IndexMetadata {
+    pub model: String,
+    pub dimensions: usize,
+    pub metric: String,
+    pub quantization: VectorQuantization,
+    #[serde(default)]
+    pub backend: Backend,
+    #[serde(default)]
+    pub connectivity: usize,
+    #[serde(default)]
+    pub expansion_add: usize,
+    #[serde(default)]
+    pub keys: DocumentKeys,
+}
+
+impl IndexMetadata {
+    pub fn new(model: &str, dimensions: usize, index: &IndexConfig) -> IndexMetadata {
+        IndexMetadata {
+            model: model.to_string(),
+            dimensions,
+            metric: "cos".to_string(),
+            quantization: index.quantization,
+            backend: index.backend,
+            connectivity: index.connectivity,
+            expansion_add: index.expansion_add,
+            keys: DocumentKeys::Stable,
+        }
+    }
+
+    /// Whether an index built as described needs rebuilding to match `config`.
+    /// `expansion_search` only affects queries, so it never forces a rebuild.
+    /// Indexes keyed by row id are always rebuilt.
+    pub fn is_stale(&self, config: &Config) -> bool {
+        *self != IndexMetadata::new(&config.model, self.dimensions, &config.index)
+    }
+}
+
+/// Path of the store called `name` in the backend recorded in `metadata`.
+pub fn store_path(config: &Config, metadata: &IndexMetadata, name: &str) -> String {
+    config.data_path(&format!("{}.{}", name, metadata.backend.extension()))
+}
+
+/// Reads `index.json`. Indexes written before it existed were always built
+/// with MiniLM, so those are adopted with MiniLM metadata.
+pub fn load_metadata(config: &Config) -> Option<IndexMetadata> {
+    let metadata_path = config.data_path(METADATA_FILE);
+
+    if Path::new(&metadata_path).exists() {
+        let metadata = std::fs::read_to_string(metadata_path).unwrap();
+        Some(serde_json::from_str(&metadata).unwrap())
+    } else if Path::new(&config.data_path(LEGACY_INDEX_FILE)).exists() {
+        let metadata = IndexMetadata {
+            keys: DocumentKeys::RowId,
+            ..IndexMetadata::new(MiniLM::MODEL_ID, 384, &IndexConfig::default())
+        };
+        save_metadata(config, &metadata);
+        Some(metadata)
+    } else {
+        None
+    }
+}
+
+pub fn save_metadata(config: &Config, metadata: &IndexMetadata) {
+    std::fs::write(
+        config.data_path(METADATA_FILE),
+        serde_json::to_string_pretty(metadata).unwrap(),
+    )
+    .unwrap();
+}
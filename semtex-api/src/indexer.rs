@@ -1,21 +1,48 @@
 use actix::dev::{MessageResponse, OneshotSender};
 use actix::prelude::*;
 use actix_web::rt::Runtime;
-use semtex_vector::embed;
-use semtex_vector::minilm::MiniLM;
+use entity::content;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
 
-use crate::Models;
-use crate::searcher::SearcherActor;
+use crate::alerts;
+use crate::config::Config;
+use crate::embedding::EmbeddingService;
+use crate::index::{store_path, IndexMetadata};
+use crate::live::LiveIndex;
+use crate::store::{open_store, VectorStore};
+use crate::vectors::{record_vectors, save_vectors};
+
+const MIGRATION_NAME: &str = "index.migration";
+const MIGRATION_BATCH_SIZE: u64 = 32;
+const DEFAULT_MERGE_THRESHOLD: usize = 10_000;
 
 #[derive(Message)]
 #[rtype(result = "IndexResponse")]
 pub enum IndexMessage {
-    Index { key: u64, text: String },
+    Index { items: Vec<IndexItem> },
+    /// Removes the vectors stored under `keys`, also from an index being
+    /// rebuilt.
+    Remove { keys: Vec<u64> },
+    /// Adds the next batch of content to the index being rebuilt.
+    Migrate,
+    /// Starts rebuilding the live index from its own vectors, unless a
+    /// rebuild is already running.
+    Compact,
+    /// Finishes any rebuild in progress, so that once it is answered every
+    /// message sent before it has been handled and the index is settled.
+    Flush,
+}
+
+pub struct IndexItem {
+    pub key: u64,
+    pub title: String,
+    pub text: String,
 }
 
 #[derive(Debug)]
 pub enum IndexResponse {
     IndexResult,
+    Compaction { started: bool },
 }
 
 impl<A, M> MessageResponse<A, M> for IndexResponse
@@ -30,48 +57,248 @@ where
     }
 }
 
+/// A shadow index replacing the live one, either for a newly configured
+/// model or backend, or to compact the live index. It is built batch by batch
+/// between ingest messages, in content id order, so rows ingested meanwhile
+/// are picked up once the cursor reaches them. It is swapped in once it has
+/// caught up.
+struct Migration {
+    metadata: IndexMetadata,
+    embeddings: EmbeddingService,
+    store: Box<dyn VectorStore>,
+    cursor: i32,
+}
+
+impl Migration {
+    fn start(config: &Config, metadata: IndexMetadata, embeddings: EmbeddingService) -> Migration {
+        let migration_path = store_path(config, &metadata, MIGRATION_NAME);
+        let _ = std::fs::remove_file(&migration_path);
+
+        Migration {
+            store: open_store(&metadata, &migration_path),
+            metadata,
+            embeddings,
+            cursor: 0,
+        }
+    }
+}
+
 pub struct IndexerActor {
-    models: Models,
-    searcher: Addr<SearcherActor>,
-    minilm: MiniLM,
+    config: Config,
+    db: DatabaseConnection,
+    live: LiveIndex,
+    embeddings: EmbeddingService,
+    migration: Option<Migration>,
+    alert_vectors: alerts::QueryVectors,
 }
 
 impl Actor for IndexerActor {
     type Context = SyncContext<Self>;
 
-    fn started(&mut self, _ctx: &mut SyncContext<Self>) {
-        println!("Actor is alive");
+    fn started(&mut self, ctx: &mut SyncContext<Self>) {
+        log::info!("Actor is alive");
+
+        if self.migration.is_some() {
+            self.live.set_rebuilding(true);
+            ctx.address().do_send(IndexMessage::Migrate);
+        } else if self.needs_merge() || self.has_stale_delta() {
+            ctx.address().do_send(IndexMessage::Compact);
+        }
     }
 
     fn stopped(&mut self, _ctx: &mut SyncContext<Self>) {
-        println!("Actor is stopped");
+        log::info!("Actor is stopped");
     }
 }
 
-pub fn indexer(models: &Models, searcher: &Addr<SearcherActor>) -> IndexerActor {
+pub fn indexer(
+    config: &Config,
+    metadata: &IndexMetadata,
+    db: &DatabaseConnection,
+    embeddings: &EmbeddingService,
+    migration_embeddings: Option<EmbeddingService>,
+    live: &LiveIndex,
+) -> IndexerActor {
+    let migration = migration_embeddings.map(|embeddings| {
+        let target = IndexMetadata::new(&config.model, embeddings.dimensions(), &config.index);
+        log::info!("migrating index from {:?} to {:?}", metadata, target);
+
+        Migration::start(config, target, embeddings)
+    });
+
     IndexerActor {
-        models: models.clone(),
-        searcher: searcher.clone(),
-        minilm: MiniLM::new(),
+        config: config.clone(),
+        db: db.clone(),
+        live: live.clone(),
+        embeddings: embeddings.clone(),
+        migration,
+        alert_vectors: alerts::QueryVectors::default(),
+    }
+}
+
+impl IndexerActor {
+    /// Whether the delta of a memory-mapped index has grown enough to be
+    /// merged by a compaction.
+    fn needs_merge(&self) -> bool {
+        if !self.config.index.memory_mapped || self.migration.is_some() {
+            return false;
+        }
+        let threshold = match self.config.index.merge_threshold {
+            0 => DEFAULT_MERGE_THRESHOLD,
+            threshold => threshold,
+        };
+        self.live.stats().1.delta >= threshold
+    }
+
+    /// Whether an index last used memory-mapped, but no longer configured so,
+    /// still has a delta to merge before it is loaded into memory.
+    fn has_stale_delta(&self) -> bool {
+        !self.config.index.memory_mapped
+            && self.migration.is_none()
+            && self.live.stats().1.delta > 0
+    }
+
+    /// Adds the next batch of content to the index being rebuilt, or swaps it
+    /// in once every record is in it. Returns whether batches remain.
+    fn migrate_batch(&mut self, rt: &Runtime) -> bool {
+        let Some(migration) = self.migration.as_mut() else {
+            return false;
+        };
+
+        let records = rt
+            .block_on(
+                content::Entity::find()
+                    .filter(content::Column::Id.gt(migration.cursor))
+                    .order_by_asc(content::Column::Id)
+                    .limit(MIGRATION_BATCH_SIZE)
+                    .all(&self.db),
+            )
+            .unwrap();
+
+        if !records.is_empty() {
+            // A compaction keeps the model and keys, so vectors not yet
+            // stored in the database can be copied from the live index.
+            let live_metadata = self.live.metadata();
+            let reuse = migration.metadata.model == live_metadata.model
+                && migration.metadata.keys == live_metadata.keys;
+            let live = &self.live;
+            let vectors = rt.block_on(record_vectors(
+                &self.db,
+                &migration.metadata.model,
+                &migration.embeddings,
+                &records,
+                |key| live.get(key).filter(|_| reuse),
+            ));
+
+            for (key, vector) in vectors.iter() {
+                migration.store.add(*key, vector);
+            }
+
+            migration.cursor = records.last().unwrap().id;
+            return true;
+        }
+
+        // Every record up to now is in the migration index, and no
+        // ingest can interleave until the handler calling this returns.
+        let migration = self.migration.take().unwrap();
+        log::info!(
+            "rebuild with {} complete ({} vectors)",
+            migration.metadata.model,
+            migration.store.stats().size
+        );
+
+        let migration_path = store_path(&self.config, &migration.metadata, MIGRATION_NAME);
+        migration.store.persist(&migration_path);
+        self.live.swap(
+            migration.metadata,
+            migration.store,
+            migration.embeddings.clone(),
+            &migration_path,
+        );
+        self.live.set_rebuilding(false);
+        self.embeddings = migration.embeddings;
+
+        false
     }
 }
 
 impl Handler<IndexMessage> for IndexerActor {
     type Result = IndexResponse;
 
-    fn handle(&mut self, msg: IndexMessage, _ctx: &mut SyncContext<Self>) -> Self::Result {
+    fn handle(&mut self, msg: IndexMessage, ctx: &mut SyncContext<Self>) -> Self::Result {
         let rt = Runtime::new().unwrap();
         match msg {
-            IndexMessage::Index { key, text } => {
-                let v = embed(&mut self.minilm, &[&text]);
+            IndexMessage::Index { items } => {
+                let documents = items
+                    .iter()
+                    .map(|item| (item.title.as_str(), item.text.as_str()))
+                    .collect::<Vec<_>>();
+                let vectors = self.embeddings.embed_documents(&documents);
+                let entries = items
+                    .iter()
+                    .map(|item| item.key)
+                    .zip(vectors)
+                    .collect::<Vec<_>>();
+
+                rt.block_on(save_vectors(&self.db, &self.live.metadata().model, &entries));
+                self.live.add(&entries);
+                rt.block_on(alerts::record_matches(
+                    &self.db,
+                    &self.config.alerts,
+                    &self.live,
+                    &mut self.alert_vectors,
+                    &entries,
+                ));
 
-                rt.block_on(self.searcher.send(crate::searcher::SearchMessage::Index {
-                    key,
-                    vector: v[0].clone(),
-                })).unwrap();
+                if self.needs_merge() {
+                    ctx.address().do_send(IndexMessage::Compact);
+                }
 
                 return IndexResponse::IndexResult;
             }
+            IndexMessage::Remove { keys } => {
+                // A rebuild may already have copied the vectors.
+                if let Some(migration) = self.migration.as_mut() {
+                    for key in keys.iter() {
+                        migration.store.remove(*key);
+                    }
+                }
+                self.live.remove(&keys);
+
+                if self.needs_merge() {
+                    ctx.address().do_send(IndexMessage::Compact);
+                }
+
+                IndexResponse::IndexResult
+            }
+            IndexMessage::Migrate => {
+                if self.migrate_batch(&rt) {
+                    ctx.address().do_send(IndexMessage::Migrate);
+                }
+                IndexResponse::IndexResult
+            }
+            IndexMessage::Compact => {
+                if self.migration.is_some() {
+                    return IndexResponse::Compaction { started: false };
+                }
+
+                let metadata = self.live.metadata();
+                log::info!("compacting index ({:?})", metadata);
+                self.migration = Some(Migration::start(
+                    &self.config,
+                    metadata,
+                    self.embeddings.clone(),
+                ));
+                self.live.set_rebuilding(true);
+                ctx.address().do_send(IndexMessage::Migrate);
+
+                IndexResponse::Compaction { started: true }
+            }
+            IndexMessage::Flush => {
+                // Migrate messages still queued find nothing left to do.
+                while self.migrate_batch(&rt) {}
+                IndexResponse::IndexResult
+            }
         }
     }
 }
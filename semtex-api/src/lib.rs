@@ -1,35 +1,65 @@
+pub mod alerts;
+pub mod config;
+pub mod diversify;
+pub mod doctor;
+mod embedding;
+pub mod eval;
+pub mod explain;
+pub mod history;
+pub mod index;
 mod indexer;
+mod live;
+pub mod query;
+pub mod query_cache;
+pub mod ranking;
+mod reranker;
 mod searcher;
+pub mod snippet;
+pub mod store;
 mod util;
+mod vectors;
 
-use core::panic;
 use actix_cors::Cors;
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::fmt::Debug;
-use std::time::Instant;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use actix::{Addr, SyncArbiter};
 use actix_web::middleware::Logger;
-use actix_web::{get, post, web, App, HttpResponse, HttpServer, Responder};
-use chrono::Utc;
+use actix_web::{delete, get, post, put, web, App, HttpResponse, HttpServer, Responder};
+use chrono::{DateTime, Utc};
+use config::{load_config, Config, DiversifyConfig, RankingConfig, RerankConfig};
 use entity::content;
 use env_logger::Env;
+use embedding::EmbeddingService;
+use explain::{millis, Explanation, FilterDecision, HitExplanation, SearchTrace};
+use history::{HistoryEntry, NewSavedSearch, SavedSearch, SavedSearchChanges};
+use index::{load_metadata, AllowedDocuments, DocumentKeys, IndexMetadata};
 use indexer::{indexer, IndexerActor};
+use live::LiveIndex;
+use reranker::{reranker, RerankerActor};
 use migration::{Migrator, MigratorTrait};
-use rand::RngCore;
+use query::Query;
+use query_cache::QueryCacheStats;
+use ranking::{Score, Signals};
+use sea_orm::sea_query::{Expr, LikeExpr};
 use sea_orm::{
-    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, DatabaseConnection, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
 };
 use searcher::{searcher, SearcherActor};
-use semtex_vector::jina_candle::{self, JinaCandle};
-use semtex_vector::minilm::MiniLM;
+use semtex_vector::cross_encoder::Reranker;
+use semtex_vector::load_reranker;
 use serde::{Deserialize, Serialize};
-use util::xdg_dirs;
+use snippet::Snippet;
 
 #[derive(Deserialize)]
-struct Source {
-    name: String,
-    url: Option<String>,
+pub struct Source {
+    pub name: String,
+    pub url: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -38,149 +68,1336 @@ struct Ingest {
 }
 
 #[derive(Deserialize)]
-struct IngestItem {
-    title: String,
-    content: String,
-    source: Source,
+pub struct IngestItem {
+    pub title: String,
+    pub content: String,
+    pub source: Source,
 }
 
 #[derive(Serialize)]
 pub struct SearchResult {
-    key: i32,
-    title: String,
-    text: String,
-    url: Option<String>,
-    distance: f32,
+    /// Serialized as a string, since JavaScript numbers cannot hold every key.
+    #[serde(serialize_with = "util::serialize_display")]
+    pub key: u64,
+    pub title: String,
+    /// The full text, left out of responses in favour of `snippets`; clients
+    /// fetch it from `/content/{key}` when needed.
+    #[serde(skip)]
+    pub text: String,
+    /// The passages best matching the query, or the opening passages of
+    /// results not found by a query.
+    pub snippets: Vec<Snippet>,
+    pub url: Option<String>,
+    /// Name of the source the page was stored from.
+    pub source: String,
+    /// When the page was stored, as RFC 3339.
+    pub created_at: String,
+    pub distance: f32,
+    /// Cross-encoder relevance, if the result was reranked in time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rerank_score: Option<f32>,
+    /// The parts of the ranking score, if results were ranked by more than
+    /// similarity.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<Score>,
+    /// The index vector, when the search needed it to diversify results.
+    #[serde(skip)]
+    pub vector: Option<Vec<f32>>,
+    /// How the result was scored, if the search was explained.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explain: Option<HitExplanation>,
 }
 
-#[derive(Deserialize)]
-struct Search {
-    query: String,
+/// A search and its per-request options, as `/search` query parameters.
+#[derive(Default, Deserialize)]
+pub struct SearchParams {
+    /// Free text and the operators described in `query`. Running a saved
+    /// search takes its query instead.
+    #[serde(default)]
+    pub query: String,
+    /// Overrides the configured HNSW search breadth for this query.
+    pub expansion_search: Option<usize>,
+    /// Whether to rerank the results, overriding `rerank.by_default`.
+    pub rerank: Option<bool>,
+    /// Whether to rank by recency, visits and source as configured under
+    /// `ranking`, rather than by similarity alone. On by default.
+    pub rank: Option<bool>,
+    /// Whether to diversify the results, overriding `diversify.by_default`.
+    pub diversify: Option<bool>,
+    /// Overrides `diversify.lambda` for this search.
+    pub lambda: Option<f32>,
+    /// Overrides `diversify.max_per_domain` for this search.
+    pub max_per_domain: Option<usize>,
+    /// Results to skip, for fetching later pages.
+    pub offset: Option<usize>,
+    /// Results per page, `RESULT_COUNT` by default and `MAX_RESULT_COUNT` at most.
+    pub limit: Option<usize>,
+    /// Whether to explain how each result was scored and how long each
+    /// stage took.
+    pub explain: Option<bool>,
+}
+
+impl SearchParams {
+    pub fn new(query: &str) -> SearchParams {
+        SearchParams {
+            query: query.to_string(),
+            ..SearchParams::default()
+        }
+    }
+}
+
+/// Options for `/content/{key}/similar`, as query parameters.
+#[derive(Default, Deserialize)]
+pub struct SimilarParams {
+    /// Operators restricting the results, as in a search query. Free text in
+    /// it is ignored.
+    pub filter: Option<String>,
+    /// Overrides the configured HNSW search breadth for this search.
+    pub expansion_search: Option<usize>,
+    /// Results to skip, for fetching later pages.
+    pub offset: Option<usize>,
+    /// Results per page, `RESULT_COUNT` by default and `MAX_RESULT_COUNT` at most.
+    pub limit: Option<usize>,
+}
+
+/// The positions of the results on the requested page.
+fn page(offset: Option<usize>, limit: Option<usize>) -> Range<usize> {
+    let offset = offset.unwrap_or(0);
+    offset..offset + limit.unwrap_or(RESULT_COUNT).min(MAX_RESULT_COUNT)
 }
 
 #[derive(Serialize)]
 struct SearchResults {
+    /// The id of the search in the history, for recording which results
+    /// are opened; only the first page of a search is recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    search_id: Option<i32>,
     results: Vec<SearchResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explain: Option<Explanation>,
 }
 
-struct AppState {
+pub struct AppState {
+    config: Config,
     searcher: Addr<SearcherActor>,
     indexer: Addr<IndexerActor>,
+    reranker: Option<Addr<RerankerActor>>,
     db: DatabaseConnection,
+    /// Whether searches are recorded in the history.
+    history_enabled: AtomicBool,
 }
 
-#[derive(Clone)]
-pub struct Models {
-    // jina_candle: JinaCandle,
-}
+/// Results returned per page unless a `limit` is given.
+const RESULT_COUNT: usize = 10;
+/// The largest `limit` a page may have.
+const MAX_RESULT_COUNT: usize = 100;
+/// Results this close to the document a similar-content search started from
+/// are copies of it, even if stored under another key.
+const DUPLICATE_DISTANCE: f32 = 0.01;
+/// Sentences per result, those with the most query terms, considered when
+/// choosing snippets.
+const SNIPPET_CANDIDATES: usize = 8;
+/// The best of those per result that are also compared with the query by
+/// embedding, if `[snippets] semantic` is on. Each is a model pass ahead of
+/// queued documents, so there are few.
+const SEMANTIC_CANDIDATES: usize = 3;
 
 #[get("/")]
 async fn root() -> impl Responder {
     HttpResponse::Ok().body("semtex")
 }
 
-#[post("/ingest")]
-async fn ingest(ingest: web::Json<Ingest>, data: web::Data<AppState>) -> impl Responder {
-    for item in &ingest.items {
+/// Stores `items` and adds them to the index, returning their content keys.
+pub async fn ingest_items(data: &AppState, items: &[IngestItem]) -> Vec<u64> {
+    let mut keys = Vec::new();
+    let mut index_items = Vec::new();
+
+    for item in items {
+        let key = rand::random::<u64>();
         let record = content::ActiveModel {
             id: ActiveValue::NotSet,
+            key: ActiveValue::Set(key as i64),
             created_at: ActiveValue::Set(Utc::now().to_rfc3339()),
             title: ActiveValue::Set(item.title.to_owned()),
             text: ActiveValue::Set(item.content.to_owned()),
             source: ActiveValue::Set(item.source.name.to_owned()),
             url: ActiveValue::Set(item.source.url.to_owned()),
+            embedding: ActiveValue::NotSet,
+            embedding_model: ActiveValue::NotSet,
         };
 
-        let result = record.insert(&data.db).await;
-        let key = result.unwrap().id;
+        record.insert(&data.db).await.unwrap();
 
-        data.indexer
-            .send(indexer::IndexMessage::Index {
-                key: key as u64,
-                text: item.content.clone(),
-            })
-            .await
-            .unwrap();
+        keys.push(key);
+        index_items.push(indexer::IndexItem {
+            key,
+            title: item.title.clone(),
+            text: item.content.clone(),
+        });
     }
 
-    format!("ingested {}", ingest.items.len())
+    // Sent as one batch so the embedding workers can share the load.
+    data.indexer
+        .send(indexer::IndexMessage::Index { items: index_items })
+        .await
+        .unwrap();
+
+    keys
 }
 
-#[get("/search")]
-async fn search(search: web::Query<Search>, data: web::Data<AppState>) -> impl Responder {
+/// Deletes the row stored under `key`, with its alerts, and removes its vector
+/// from the index. Returns whether there was such a row.
+pub async fn remove_content(data: &AppState, key: u64) -> bool {
+    let deleted = content::Entity::delete_many()
+        .filter(content::Column::Key.eq(key as i64))
+        .exec(&data.db)
+        .await
+        .unwrap()
+        .rows_affected;
+    if deleted == 0 {
+        return false;
+    }
+
+    data.indexer
+        .send(indexer::IndexMessage::Remove { keys: vec![key] })
+        .await
+        .unwrap();
+    true
+}
+
+/// Waits until the indexer has handled everything sent to it so far and
+/// finished any migration or compaction in progress.
+pub async fn flush_index(data: &AppState) {
+    data.indexer.send(indexer::IndexMessage::Flush).await.unwrap();
+}
+
+/// Searches the index, nearest content first, or most relevant first if
+/// reranked. A query of only operators lists the pages matching them, newest
+/// first.
+pub async fn search_content(data: &AppState, params: &SearchParams) -> Vec<SearchResult> {
+    explain_search(data, params).await.0
+}
+
+/// Searches as `search_content` does, also explaining the search. Results
+/// explain their scores only if `params.explain` is set.
+pub async fn explain_search(
+    data: &AppState,
+    params: &SearchParams,
+) -> (Vec<SearchResult>, Explanation) {
+    let started = Instant::now();
+    let explain = params.explain.unwrap_or(false);
+    let query = Query::parse(&params.query, Utc::now());
+    let page = page(params.offset, params.limit);
+    let mut explanation = Explanation {
+        text: query.text.clone(),
+        ..Explanation::default()
+    };
+
+    if explain {
+        for (operator, query) in query.operators() {
+            let matching = count_content(&data.db, content_filter(&data.db, &query).await).await;
+            explanation
+                .filters
+                .push(FilterDecision { operator, matching });
+        }
+    }
+
+    let filtering = Instant::now();
+    if query.text.is_empty() && query.has_filters() {
+        let matching = count_content(&data.db, content_filter(&data.db, &query).await).await;
+        explanation.timings.filter = Some(millis(filtering.elapsed()));
+        explanation.allowed = Some(matching);
+        let fetching = Instant::now();
+        let mut results = latest_content(data, &query, page).await;
+        explanation.timings.fetch = millis(fetching.elapsed());
+        explanation.candidates = matching;
+        if explain {
+            for result in results.iter_mut() {
+                result.explain = Some(HitExplanation::default());
+            }
+        }
+        add_timed_snippets(data, &query, &mut results, &mut explanation).await;
+        explanation.timings.total = millis(started.elapsed());
+        return (results, explanation);
+    }
+    let allowed = if query.has_filters() {
+        let allowed = allowed_documents(&data.db, &query).await;
+        explanation.timings.filter = Some(millis(filtering.elapsed()));
+        explanation.allowed = Some(allowed.keys.len());
+        Some(allowed)
+    } else {
+        None
+    };
+
+    let rerank = match (&data.config.rerank, &data.reranker) {
+        (Some(config), Some(reranker)) if params.rerank.unwrap_or(config.by_default) => {
+            Some((config, reranker))
+        }
+        _ => None,
+    };
+    let ranking = data
+        .config
+        .ranking
+        .as_ref()
+        .filter(|_| params.rank.unwrap_or(true));
+    let diversify = data
+        .config
+        .diversify
+        .as_ref()
+        .filter(|config| params.diversify.unwrap_or(config.by_default));
+    let count = [
+        Some(page.end),
+        rerank.map(|(config, _)| config.candidates),
+        ranking.map(|config| config.candidates),
+        diversify.map(|config| config.candidates),
+    ]
+    .into_iter()
+    .flatten()
+    .max()
+    .unwrap();
+
     let response = data
         .searcher
         .send(searcher::SearchMessage::Search {
-            query: search.query.clone(),
+            query: query.text.clone(),
+            count,
+            expansion_search: params.expansion_search,
+            allowed,
+            with_vectors: diversify.is_some(),
         })
         .await
         .unwrap();
 
+    let fetching = Instant::now();
+    let (mut results, trace) = load_results(data, response).await;
+    explanation.embedding_norm = Some(trace.query_norm);
+    explanation.timings.embed = millis(trace.embed);
+    explanation.timings.search = millis(trace.search);
+    explanation.timings.fetch = millis(fetching.elapsed());
+    explanation.candidates = results.len();
+    if explain {
+        for (nearest_rank, result) in results.iter_mut().enumerate() {
+            result.explain = Some(HitExplanation {
+                nearest_rank,
+                ..HitExplanation::default()
+            });
+        }
+    }
+
+    if let Some((config, reranker)) = rerank {
+        let reranking = Instant::now();
+        results = rerank_results(reranker, config, &query.text, results).await;
+        explanation.timings.rerank = Some(millis(reranking.elapsed()));
+    }
+    if let Some(config) = ranking {
+        let ranking = Instant::now();
+        results = rank_results(data, config, results).await;
+        explanation.timings.rank = Some(millis(ranking.elapsed()));
+    }
+    if let Some(config) = diversify {
+        let diversifying = Instant::now();
+        results = diversify_results(config, params, results);
+        explanation.timings.diversify = Some(millis(diversifying.elapsed()));
+        explanation.dropped_by_domain = explanation.candidates - results.len();
+    }
+    if explain {
+        let relevance = relevance(&results);
+        for (result, relevance) in results.iter_mut().zip(relevance) {
+            result.explain.as_mut().unwrap().fused_score = relevance;
+        }
+    }
+
+    let mut results = results
+        .into_iter()
+        .skip(page.start)
+        .take(page.len())
+        .collect::<Vec<_>>();
+    add_timed_snippets(data, &query, &mut results, &mut explanation).await;
+    explanation.timings.total = millis(started.elapsed());
+    (results, explanation)
+}
+
+/// Adds snippets to `results` and, if they are explained, their lexical
+/// scores.
+async fn add_timed_snippets(
+    data: &AppState,
+    query: &Query,
+    results: &mut [SearchResult],
+    explanation: &mut Explanation,
+) {
+    let started = Instant::now();
+    explanation.embedded_sentences = add_snippets(data, Some(query), results).await;
+    explanation.timings.snippets = millis(started.elapsed());
+
+    let terms = snippet::terms(&format!("{} {}", query.text, query.phrases.join(" ")));
+    for result in results.iter_mut() {
+        let text = format!("{}\n{}", result.title, result.text);
+        if let Some(explain) = result.explain.as_mut() {
+            explain.lexical_score = snippet::lexical_score(&text, &terms);
+        }
+    }
+}
+
+/// The rows matching the operators in `query`. Sites are matched on the host
+/// of the url, or a subdomain of it, and excluded terms on whole words.
+async fn content_filter(db: &DatabaseConnection, query: &Query) -> Condition {
+    let like = |text: &str, pattern: &str| {
+        let escaped = text
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        LikeExpr::new(pattern.replace("{}", &escaped)).escape('\\')
+    };
+    let contains = |text: &str| {
+        Condition::any()
+            .add(Expr::col(content::Column::Title).like(like(text, "%{}%")))
+            .add(Expr::col(content::Column::Text).like(like(text, "%{}%")))
+    };
+    let on_site = |site: &str| {
+        let mut on_site = Condition::any();
+        for host in ["://{}", "://%.{}"] {
+            for end in ["", "/%", ":%", "?%", "#%"] {
+                let pattern = format!("%{}{}", host, end);
+                on_site = on_site.add(Expr::col(content::Column::Url).like(like(site, &pattern)));
+            }
+        }
+        on_site
+    };
+
+    let mut filter = Condition::all();
+    if !query.sources.is_empty() {
+        filter = filter.add(content::Column::Source.is_in(query.sources.clone()));
+    }
+    if !query.sites.is_empty() {
+        filter = filter.add(
+            query
+                .sites
+                .iter()
+                .fold(Condition::any(), |any, site| any.add(on_site(site))),
+        );
+    }
+    if let Some(after) = query.after {
+        filter = filter.add(content::Column::CreatedAt.gte(after.to_rfc3339()));
+    }
+    if let Some(before) = query.before {
+        filter = filter.add(content::Column::CreatedAt.lt(before.to_rfc3339()));
+    }
+    for phrase in query.phrases.iter() {
+        filter = filter.add(contains(phrase));
+    }
+    for term in query.excluded.iter() {
+        // `LIKE` also matches inside words, so check the pages it finds.
+        let excluded = content::Entity::find()
+            .select_only()
+            .column(content::Column::Key)
+            .column(content::Column::Title)
+            .column(content::Column::Text)
+            .filter(contains(term))
+            .into_tuple::<(i64, String, String)>()
+            .all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|(_, title, text)| {
+                snippet::contains_words(title, term) || snippet::contains_words(text, term)
+            })
+            .map(|(key, _, _)| key);
+        filter = filter.add(content::Column::Key.is_not_in(excluded));
+    }
+    filter
+}
+
+/// Keys of the rows matching the operators in `query`.
+async fn allowed_documents(db: &DatabaseConnection, query: &Query) -> AllowedDocuments {
+    let rows = content::Entity::find()
+        .select_only()
+        .column(content::Column::Key)
+        .column(content::Column::Id)
+        .filter(content_filter(db, query).await)
+        .into_tuple::<(i64, i32)>()
+        .all(db)
+        .await
+        .unwrap();
+
+    let mut allowed = AllowedDocuments::default();
+    for (key, id) in rows.iter() {
+        allowed.keys.insert(*key as u64);
+        allowed.ids.insert(*id as u64);
+    }
+    allowed
+}
+
+/// How many rows `filter` matches.
+async fn count_content(db: &DatabaseConnection, filter: Condition) -> usize {
+    content::Entity::find()
+        .filter(filter)
+        .count(db)
+        .await
+        .unwrap() as usize
+}
+
+/// The `page` of the rows matching the operators in `query`, newest first.
+/// Without text to compare them with, their `distance` is 1.
+async fn latest_content(data: &AppState, query: &Query, page: Range<usize>) -> Vec<SearchResult> {
+    content::Entity::find()
+        .filter(content_filter(&data.db, query).await)
+        .order_by_desc(content::Column::CreatedAt)
+        .order_by_desc(content::Column::Id)
+        .offset(page.start as u64)
+        .limit(page.len() as u64)
+        .all(&data.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| SearchResult {
+            key: r.key as u64,
+            title: r.title,
+            text: r.text,
+            snippets: Vec::new(),
+            url: r.url,
+            source: r.source,
+            created_at: r.created_at,
+            distance: 1.0,
+            rerank_score: None,
+            score: None,
+            vector: None,
+            explain: None,
+        })
+        .collect()
+}
+
+/// Reorders `results` by maximal marginal relevance, leaving out those over
+/// the per-domain cap.
+fn diversify_results(
+    config: &DiversifyConfig,
+    params: &SearchParams,
+    results: Vec<SearchResult>,
+) -> Vec<SearchResult> {
+    let lambda = params.lambda.unwrap_or(config.lambda).clamp(0.0, 1.0);
+    let max_per_domain =
+        Some(params.max_per_domain.unwrap_or(config.max_per_domain)).filter(|&max| max > 0);
+
+    let order = {
+        let candidates = results
+            .iter()
+            .zip(relevance(&results))
+            .map(|(r, relevance)| diversify::Candidate {
+                relevance,
+                vector: r.vector.as_deref().unwrap(),
+                domain: r.url.as_deref().and_then(diversify::domain),
+            })
+            .collect::<Vec<_>>();
+        diversify::mmr(&candidates, lambda, max_per_domain)
+    };
+
+    let mut results = results.into_iter().map(Some).collect::<Vec<_>>();
+    order
+        .into_iter()
+        .map(|i| results[i].take().unwrap())
+        .collect()
+}
+
+/// Scores the first `config.candidates` of `results` by similarity, recency,
+/// visits and source, highest first; the rest follow in their order. Every
+/// visit stores the page again, so a page's visits are the rows sharing its
+/// url, and the newest of them is its last visit.
+async fn rank_results(
+    data: &AppState,
+    config: &RankingConfig,
+    mut results: Vec<SearchResult>,
+) -> Vec<SearchResult> {
+    let rest = results.split_off(config.candidates.min(results.len()));
+    let urls = results
+        .iter()
+        .filter_map(|r| r.url.clone())
+        .collect::<Vec<_>>();
+    let visits_by_url = content::Entity::find()
+        .select_only()
+        .column(content::Column::Url)
+        .column_as(Expr::col(content::Column::Id).count(), "visits")
+        .column_as(Expr::col(content::Column::CreatedAt).max(), "visited_at")
+        .filter(content::Column::Url.is_in(urls))
+        .group_by(content::Column::Url)
+        .into_tuple::<(String, i64, String)>()
+        .all(&data.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(url, visits, visited_at)| (url, (visits, visited_at)))
+        .collect::<HashMap<_, _>>();
+
+    let now = Utc::now();
+    let similarities = relevance(&results);
+    for (result, similarity) in results.iter_mut().zip(similarities) {
+        let (visits, visited_at) = result
+            .url
+            .as_ref()
+            .and_then(|url| visits_by_url.get(url))
+            .cloned()
+            .unwrap_or((1, result.created_at.clone()));
+        let visited_at = DateTime::parse_from_rfc3339(&visited_at).unwrap();
+        let signals = Signals {
+            similarity,
+            age_days: (now - visited_at.with_timezone(&Utc)).num_seconds() as f32 / 86_400.0,
+            visits: visits as u32,
+            source: &result.source,
+        };
+        result.score = Some(ranking::score(config, &signals));
+    }
+
+    results.sort_by(|a, b| {
+        let total = |r: &SearchResult| r.score.as_ref().unwrap().total;
+        total(b).total_cmp(&total(a))
+    });
+    results.extend(rest);
+    results
+}
+
+/// How well each of `results` matches the query, from 0 to 1: its ranking
+/// score or, failing that, its rerank score, scaled between the lowest and
+/// highest, and otherwise its cosine similarity.
+fn relevance(results: &[SearchResult]) -> Vec<f32> {
+    if results.iter().any(|r| r.score.is_some()) {
+        return scaled(results, |r| r.score.as_ref().map(|score| score.total));
+    }
+    if results.iter().any(|r| r.rerank_score.is_some()) {
+        return scaled(results, |r| r.rerank_score);
+    }
+    results.iter().map(|r| 1.0 - r.distance).collect()
+}
+
+/// `value` of each result scaled from 0 for the lowest to 1 for the highest;
+/// results without one count as 0.
+fn scaled(results: &[SearchResult], value: impl Fn(&SearchResult) -> Option<f32>) -> Vec<f32> {
+    let values = results.iter().filter_map(&value);
+    let min = values.clone().fold(f32::INFINITY, f32::min);
+    let max = values.fold(f32::NEG_INFINITY, f32::max);
+
+    results
+        .iter()
+        .map(|r| match value(r) {
+            Some(v) if max > min => (v - min) / (max - min),
+            Some(_) => 1.0,
+            None => 0.0,
+        })
+        .collect()
+}
+
+/// Content most like the row stored under `key`, nearest first, or `None` if
+/// there is no such row. The row itself and its duplicates, whether by url,
+/// by identical title and text, or by vector, are left out.
+pub async fn similar_content(
+    data: &AppState,
+    key: u64,
+    params: &SimilarParams,
+) -> Option<Vec<SearchResult>> {
+    let record = content::Entity::find()
+        .filter(content::Column::Key.eq(key as i64))
+        .one(&data.db)
+        .await
+        .unwrap()?;
+    let page = page(params.offset, params.limit);
+    let filter = Query::parse(params.filter.as_deref().unwrap_or(""), Utc::now());
+    let allowed = if filter.has_filters() {
+        Some(allowed_documents(&data.db, &filter).await)
+    } else {
+        None
+    };
+
+    let is_duplicate = |r: &SearchResult| {
+        r.key == key
+            || r.distance < DUPLICATE_DISTANCE
+            || (r.url.is_some() && r.url == record.url)
+            || (r.title == record.title && r.text == record.text)
+    };
+
+    // Duplicates are dropped after the search, so fetch more until the page
+    // is full or the index has no more to give.
+    let mut count = page.end * 2;
+    loop {
+        let response = data
+            .searcher
+            .send(searcher::SearchMessage::Similar {
+                key,
+                id: record.id as u64,
+                title: record.title.clone(),
+                text: record.text.clone(),
+                count,
+                expansion_search: params.expansion_search,
+                allowed: allowed.clone(),
+            })
+            .await
+            .unwrap();
+
+        let (hits, _) = load_results(data, response).await;
+        let exhausted = hits.len() < count;
+        let results = hits
+            .into_iter()
+            .filter(|r| !is_duplicate(r))
+            .collect::<Vec<_>>();
+        if exhausted || results.len() >= page.end {
+            let mut results = results
+                .into_iter()
+                .skip(page.start)
+                .take(page.len())
+                .collect::<Vec<_>>();
+            add_snippets(data, None, &mut results).await;
+            return Some(results);
+        }
+        count *= 2;
+    }
+}
+
+/// Picks the snippets of `results`: the passages best matching `query`, or
+/// without a query their opening passages. Phrases in the query count as
+/// terms, but only its free text is compared by embedding.
+/// Adds snippets to `results`, returning how many sentences were embedded to
+/// choose them.
+async fn add_snippets(
+    data: &AppState,
+    query: Option<&Query>,
+    results: &mut [SearchResult],
+) -> usize {
+    let config = &data.config.snippets;
+    let terms = query
+        .map(|query| snippet::terms(&format!("{} {}", query.text, query.phrases.join(" "))))
+        .unwrap_or_default();
+    let candidates = results
+        .iter()
+        .map(|r| snippet::candidates(&r.text, &terms, SNIPPET_CANDIDATES))
+        .collect::<Vec<_>>();
+    let semantic = config.semantic && query.is_some_and(|query| !query.text.is_empty());
+    let passages = results
+        .iter()
+        .zip(&candidates)
+        .flat_map(|(r, sentences)| {
+            sentences
+                .iter()
+                .take(if semantic { SEMANTIC_CANDIDATES } else { 0 })
+                .map(|s| r.text[s.clone()].to_string())
+        })
+        .collect::<Vec<_>>();
+    let embedded = passages.len();
+
+    let similarities = match query {
+        Some(query) if embedded > 0 => {
+            let response = data
+                .searcher
+                .send(searcher::SearchMessage::Similarities {
+                    query: query.text.clone(),
+                    passages,
+                })
+                .await
+                .unwrap();
+            match response {
+                searcher::SearchResponse::Similarities { similarities } => similarities,
+                _ => panic!(),
+            }
+        }
+        _ => Vec::new(),
+    };
+
+    // Candidates are best first, so the embedded ones lead each page's list.
+    let mut similarities = similarities.into_iter();
+    for (result, sentences) in results.iter_mut().zip(candidates) {
+        let scored = sentences
+            .into_iter()
+            .enumerate()
+            .map(|(i, s)| {
+                if semantic && i < SEMANTIC_CANDIDATES {
+                    (s, similarities.next().unwrap())
+                } else {
+                    (s, 0.0)
+                }
+            })
+            .collect::<Vec<_>>();
+        result.snippets = snippet::snippets(&result.text, &terms, &scored, config.passages);
+    }
+    embedded
+}
+
+/// The content rows a search hit, nearest first, and how the searcher went
+/// about it.
+async fn load_results(
+    data: &AppState,
+    response: searcher::SearchResponse,
+) -> (Vec<SearchResult>, SearchTrace) {
     match response {
-        searcher::SearchResponse::SearchResult { results } => {
-            let ids = results.iter().map(|r| r.key as i32).collect::<Vec<_>>();
+        searcher::SearchResponse::SearchResult {
+            keys,
+            results,
+            vectors,
+            trace,
+        } => {
+            // Until an index keyed by row id has been rebuilt, its hits are
+            // looked up by row id.
+            let column = match keys {
+                DocumentKeys::Stable => content::Column::Key,
+                DocumentKeys::RowId => content::Column::Id,
+            };
+            let hit_keys = results.iter().map(|r| r.key as i64).collect::<Vec<_>>();
             let records = content::Entity::find()
-                .filter(content::Column::Id.is_in(ids))
+                .filter(column.is_in(hit_keys))
                 .all(&data.db)
                 .await
                 .unwrap();
 
-            let distance_by_id = results
+            let distance_by_key = results
+                .iter()
+                .map(|r| (r.key, r.distance))
+                .collect::<HashMap<_, _>>();
+            let mut vector_by_key = results
                 .iter()
-                .map(|r| (r.key as i32, r.distance))
+                .map(|r| r.key)
+                .zip(vectors)
                 .collect::<HashMap<_, _>>();
+            let hit_key = |r: &content::Model| match keys {
+                DocumentKeys::Stable => r.key as u64,
+                DocumentKeys::RowId => r.id as u64,
+            };
 
             let mut results = records
                 .iter()
                 .map(|r| SearchResult {
-                    key: r.id,
-                    distance: distance_by_id[&r.id],
+                    key: r.key as u64,
+                    distance: distance_by_key[&hit_key(r)],
                     title: r.title.to_owned(),
                     text: r.text.to_owned(),
+                    snippets: Vec::new(),
                     url: r.url.to_owned(),
+                    source: r.source.to_owned(),
+                    created_at: r.created_at.to_owned(),
+                    rerank_score: None,
+                    score: None,
+                    vector: vector_by_key.remove(&hit_key(r)),
+                    explain: None,
                 })
                 .collect::<Vec<_>>();
 
-            results.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
-
-            web::Json(SearchResults { results })
+            results.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+            (results, trace)
         }
         _ => panic!(),
     }
 }
 
-pub async fn run_server() -> std::io::Result<()> {
-    env_logger::init_from_env(Env::default().default_filter_or("info"));
+/// Re-scores `results` with the cross-encoder, most relevant first. Results
+/// not scored within the budget follow in their original order.
+async fn rerank_results(
+    reranker: &Addr<RerankerActor>,
+    config: &RerankConfig,
+    query: &str,
+    mut results: Vec<SearchResult>,
+) -> Vec<SearchResult> {
+    let deadline = Instant::now() + Duration::from_millis(config.budget_ms);
+    let response = reranker
+        .send(reranker::RerankMessage::Rerank {
+            query: query.to_string(),
+            passages: results
+                .iter()
+                .map(|r| format!("{}\n{}", r.title, r.text))
+                .collect(),
+            deadline,
+        })
+        .await
+        .unwrap();
+
+    let reranker::RerankResponse::Scores { scores } = response;
+    for (result, score) in results.iter_mut().zip(scores) {
+        result.rerank_score = score;
+    }
+
+    results.sort_by(|a, b| match (a.rerank_score, b.rerank_score) {
+        (Some(a), Some(b)) => b.total_cmp(&a),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+    results
+}
+
+#[post("/ingest")]
+async fn ingest(ingest: web::Json<Ingest>, data: web::Data<AppState>) -> impl Responder {
+    ingest_items(&data, &ingest.items).await;
+
+    format!("ingested {}", ingest.items.len())
+}
+
+#[get("/search")]
+async fn search(search: web::Query<SearchParams>, data: web::Data<AppState>) -> impl Responder {
+    web::Json(logged_search(&data, &search).await)
+}
 
-    let models = Models {
-        // jina_candle: jina_candle::JinaCandle::new().unwrap(),
+/// Searches and, if history is on, records the first page of the search.
+async fn logged_search(data: &AppState, params: &SearchParams) -> SearchResults {
+    let (results, explanation) = explain_search(data, params).await;
+    let explain = params.explain.unwrap_or(false).then_some(explanation);
+
+    let first_page = params.offset.unwrap_or(0) == 0;
+    let search_id = if data.history_enabled.load(AtomicOrdering::Relaxed)
+        && first_page
+        && !params.query.trim().is_empty()
+    {
+        let query = Query::parse(&params.query, Utc::now());
+        Some(history::log_search(&data.db, &params.query, &query).await)
+    } else {
+        None
     };
 
-    let searcher_models = models.clone();
-    let searcher = SyncArbiter::start(1, move || searcher(&searcher_models));
+    SearchResults {
+        search_id,
+        results,
+        explain,
+    }
+}
+
+#[derive(Serialize)]
+struct Content {
+    #[serde(serialize_with = "util::serialize_display")]
+    key: u64,
+    title: String,
+    text: String,
+    source: String,
+    url: Option<String>,
+    created_at: String,
+}
+
+/// The full text of the row stored under `key`, which search results leave
+/// out.
+#[get("/content/{key}")]
+async fn get_content(key: web::Path<u64>, data: web::Data<AppState>) -> impl Responder {
+    let record = content::Entity::find()
+        .filter(content::Column::Key.eq(key.into_inner() as i64))
+        .one(&data.db)
+        .await
+        .unwrap();
 
-    let searher_addr = searcher.clone();
-    let indexer_models = models.clone();
-    let indexer = SyncArbiter::start(1, move || indexer(&indexer_models, &searher_addr));
+    match record {
+        Some(r) => HttpResponse::Ok().json(Content {
+            key: r.key as u64,
+            title: r.title,
+            text: r.text,
+            source: r.source,
+            url: r.url,
+            created_at: r.created_at,
+        }),
+        None => HttpResponse::NotFound().body("no such content"),
+    }
+}
 
-    let db_path = format!( "sqlite://{}?mode=rwc", xdg_dirs().place_data_file("db.sqlite").unwrap().display());
+#[delete("/content/{key}")]
+async fn delete_content(key: web::Path<u64>, data: web::Data<AppState>) -> impl Responder {
+    if remove_content(&data, key.into_inner()).await {
+        HttpResponse::NoContent().finish()
+    } else {
+        HttpResponse::NotFound().body("no such content")
+    }
+}
+
+/// Content like the row stored under `key`, paginated like `/search`.
+#[get("/content/{key}/similar")]
+async fn similar(
+    key: web::Path<u64>,
+    params: web::Query<SimilarParams>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match similar_content(&data, key.into_inner(), &params).await {
+        Some(results) => HttpResponse::Ok().json(SearchResults {
+            search_id: None,
+            results,
+            explain: None,
+        }),
+        None => HttpResponse::NotFound().body("no such content"),
+    }
+}
+
+#[derive(Serialize)]
+struct Stats {
+    model: String,
+    /// Whether a migration or compaction is building a replacement index.
+    rebuilding: bool,
+    #[serde(flatten)]
+    store: store::StoreStats,
+    query_cache: QueryCacheStats,
+}
+
+#[get("/stats")]
+async fn stats(data: web::Data<AppState>) -> impl Responder {
+    let response = data.searcher.send(searcher::SearchMessage::Stats).await.unwrap();
+
+    match response {
+        searcher::SearchResponse::Stats {
+            model,
+            stats,
+            rebuilding,
+            query_cache,
+        } => web::Json(Stats {
+            model,
+            rebuilding,
+            store: stats,
+            query_cache,
+        }),
+        _ => panic!(),
+    }
+}
+
+#[derive(Serialize)]
+struct Compaction {
+    /// False if a migration or compaction was already running.
+    started: bool,
+}
+
+/// Rebuilds the index from its own vectors in the background, dropping the
+/// space left by removed and replaced vectors. Searches keep using the old
+/// index until the new one is swapped in.
+#[post("/compact")]
+async fn compact(data: web::Data<AppState>) -> impl Responder {
+    let response = data
+        .indexer
+        .send(indexer::IndexMessage::Compact)
+        .await
+        .unwrap();
+
+    match response {
+        indexer::IndexResponse::Compaction { started } => web::Json(Compaction { started }),
+        _ => panic!(),
+    }
+}
+
+/// Paging of the history, as query parameters.
+#[derive(Default, Deserialize)]
+pub struct HistoryParams {
+    pub offset: Option<usize>,
+    /// Searches per page, `RESULT_COUNT` by default and `MAX_RESULT_COUNT` at
+    /// most.
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct History {
+    /// Whether searches are being recorded.
+    enabled: bool,
+    searches: Vec<HistoryEntry>,
+}
+
+/// Recorded searches, newest first.
+#[get("/history")]
+async fn get_history(
+    params: web::Query<HistoryParams>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let page = page(params.offset, params.limit);
+    web::Json(History {
+        enabled: data.history_enabled.load(AtomicOrdering::Relaxed),
+        searches: history::list(&data.db, page.start, page.len()).await,
+    })
+}
+
+#[derive(Serialize)]
+struct Deleted {
+    deleted: u64,
+}
+
+/// Clears the history.
+#[delete("/history")]
+async fn clear_history(data: web::Data<AppState>) -> impl Responder {
+    web::Json(Deleted {
+        deleted: history::delete(&data.db, None).await,
+    })
+}
+
+#[delete("/history/{id}")]
+async fn delete_history(id: web::Path<i32>, data: web::Data<AppState>) -> impl Responder {
+    match history::delete(&data.db, Some(id.into_inner())).await {
+        0 => HttpResponse::NotFound().body("no such search"),
+        deleted => HttpResponse::Ok().json(Deleted { deleted }),
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct HistoryEnabled {
+    enabled: bool,
+}
+
+/// Turns recording searches on or off. Recorded searches are kept either
+/// way.
+#[put("/history/enabled")]
+async fn set_history_enabled(
+    enabled: web::Json<HistoryEnabled>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    history::save_enabled(&data.db, enabled.enabled).await;
+    data.history_enabled
+        .store(enabled.enabled, AtomicOrdering::Relaxed);
+    web::Json(enabled.into_inner())
+}
+
+#[derive(Deserialize)]
+struct Click {
+    #[serde(deserialize_with = "util::deserialize_from_str")]
+    key: u64,
+}
+
+/// Records that a result of search `id` was opened.
+#[post("/history/{id}/clicks")]
+async fn record_click(
+    id: web::Path<i32>,
+    click: web::Json<Click>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if history::record_click(&data.db, id.into_inner(), click.key).await {
+        HttpResponse::NoContent().finish()
+    } else {
+        HttpResponse::NotFound().body("no such search")
+    }
+}
+
+#[derive(Serialize)]
+struct SavedSearches {
+    saved_searches: Vec<SavedSearch>,
+}
+
+/// Saved searches, pinned ones first.
+#[get("/saved-searches")]
+async fn list_saved_searches(data: web::Data<AppState>) -> impl Responder {
+    web::Json(SavedSearches {
+        saved_searches: history::list_saved(&data.db).await,
+    })
+}
+
+#[post("/saved-searches")]
+async fn create_saved_search(
+    new: web::Json<NewSavedSearch>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let saved = history::save_search(&data.db, &new).await;
+    HttpResponse::Created().json(saved)
+}
+
+/// Renames, changes the query of, pins or unpins a saved search.
+#[put("/saved-searches/{id}")]
+async fn update_saved_search(
+    id: web::Path<i32>,
+    changes: web::Json<SavedSearchChanges>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match history::update_saved(&data.db, id.into_inner(), &changes).await {
+        Some(saved) => HttpResponse::Ok().json(saved),
+        None => HttpResponse::NotFound().body("no such saved search"),
+    }
+}
+
+#[delete("/saved-searches/{id}")]
+async fn delete_saved_search(id: web::Path<i32>, data: web::Data<AppState>) -> impl Responder {
+    if history::delete_saved(&data.db, id.into_inner()).await {
+        HttpResponse::NoContent().finish()
+    } else {
+        HttpResponse::NotFound().body("no such saved search")
+    }
+}
+
+/// Runs a saved search, taking the other `/search` parameters. Its query
+/// replaces any `query` given.
+#[get("/saved-searches/{id}/results")]
+async fn run_saved_search(
+    id: web::Path<i32>,
+    params: web::Query<SearchParams>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match history::get_saved(&data.db, id.into_inner()).await {
+        Some(saved) => {
+            let params = SearchParams {
+                query: saved.query,
+                ..params.into_inner()
+            };
+            HttpResponse::Ok().json(logged_search(&data, &params).await)
+        }
+        None => HttpResponse::NotFound().body("no such saved search"),
+    }
+}
+
+/// Which alerts to list, as query parameters.
+#[derive(Default, Deserialize)]
+pub struct AlertParams {
+    /// Whether to leave out alerts already marked as seen.
+    pub unseen: Option<bool>,
+    pub offset: Option<usize>,
+    /// Alerts per page, `RESULT_COUNT` by default and `MAX_RESULT_COUNT` at
+    /// most.
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct Alerts {
+    alerts: Vec<alerts::Alert>,
+    /// How many alerts are unseen in all.
+    unseen: u64,
+}
+
+/// Newly indexed pages that matched saved searches with alerts on, newest
+/// first.
+#[get("/alerts")]
+async fn list_alerts(params: web::Query<AlertParams>, data: web::Data<AppState>) -> impl Responder {
+    let page = page(params.offset, params.limit);
+    let unseen = params.unseen.unwrap_or(false);
+    web::Json(Alerts {
+        alerts: alerts::list(&data.db, unseen, page.start, page.len()).await,
+        unseen: alerts::count_unseen(&data.db).await,
+    })
+}
+
+#[derive(Deserialize)]
+struct SeenAlerts {
+    /// The alerts seen; all of them if left out.
+    ids: Option<Vec<i32>>,
+}
+
+#[derive(Serialize)]
+struct Updated {
+    updated: u64,
+}
+
+#[post("/alerts/seen")]
+async fn mark_alerts_seen(
+    seen: web::Json<SeenAlerts>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    web::Json(Updated {
+        updated: alerts::mark_seen(&data.db, seen.ids.as_deref()).await,
+    })
+}
+
+/// Opens `db.sqlite` in the data directory, creating or migrating it as needed.
+pub async fn connect(config: &Config) -> DatabaseConnection {
+    let db_path = format!("sqlite://{}?mode=rwc", config.data_path("db.sqlite"));
     let connection = sea_orm::Database::connect(db_path)
         .await
         .unwrap();
     Migrator::up(&connection, None).await.unwrap();
+    connection
+}
+
+/// Opens the database and index in `config.data_dir` and starts the actors
+/// serving them. Must be called from within a running actix system.
+pub async fn start(config: &Config) -> web::Data<AppState> {
+    try_start(config).await.unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like `start`, but reports a model that is unknown or cannot be loaded, and
+/// an index that does not match its model or cannot be read.
+pub async fn try_start(config: &Config) -> Result<web::Data<AppState>, String> {
+    let metadata = load_metadata(config);
+
+    let connection = connect(config).await;
+
+    // Until a migration finishes, the live index keeps its original model.
+    let model_id = match &metadata {
+        Some(metadata) => metadata.model.clone(),
+        None => config.model.clone(),
+    };
+    let embeddings = EmbeddingService::try_start(&model_id, config)?;
+    let metadata =
+        metadata.unwrap_or_else(|| {
+            IndexMetadata::new(&model_id, embeddings.dimensions(), &config.index)
+        });
+    // The configured model is loaded here too, so that a bad one is reported
+    // before a migration to it starts.
+    let migration_embeddings = if metadata.is_stale(config) {
+        Some(EmbeddingService::try_start(&config.model, config)?)
+    } else {
+        None
+    };
+
+    let live = LiveIndex::open(config, &embeddings, &metadata)?;
+
+    let searcher_live = live.clone();
+    let searcher = SyncArbiter::start(config.search_workers.max(1), move || {
+        searcher(&searcher_live)
+    });
+
+    let indexer_config = config.clone();
+    let indexer_db = connection.clone();
+    let indexer = SyncArbiter::start(1, move || {
+        indexer(
+            &indexer_config,
+            &metadata,
+            &indexer_db,
+            &embeddings,
+            migration_embeddings.clone(),
+            &live,
+        )
+    });
+
+    if config.index.compaction_interval_hours > 0 {
+        let indexer = indexer.clone();
+        let period = Duration::from_secs(config.index.compaction_interval_hours * 60 * 60);
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(period);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                indexer.do_send(indexer::IndexMessage::Compact);
+            }
+        });
+    }
+
+    let reranker = match &config.rerank {
+        Some(rerank) => {
+            let model: Arc<dyn Reranker> = Arc::from(load_reranker(&rerank.model)?);
+            Some(SyncArbiter::start(
+                config.search_workers.max(1),
+                move || reranker(&model),
+            ))
+        }
+        None => None,
+    };
+
+    let history_enabled = history::load_enabled(&connection).await;
+    Ok(web::Data::new(AppState {
+        config: config.clone(),
+        indexer,
+        searcher,
+        reranker,
+        db: connection,
+        history_enabled: AtomicBool::new(history_enabled),
+    }))
+}
+
+/// Registers the API routes. Expects the `AppState` from `start` as app data.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(root)
+        .service(ingest)
+        .service(search)
+        .service(get_content)
+        .service(delete_content)
+        .service(similar)
+        .service(stats)
+        .service(compact)
+        .service(get_history)
+        .service(clear_history)
+        .service(delete_history)
+        .service(set_history_enabled)
+        .service(record_click)
+        .service(list_saved_searches)
+        .service(create_saved_search)
+        .service(update_saved_search)
+        .service(delete_saved_search)
+        .service(run_saved_search)
+        .service(list_alerts)
+        .service(mark_alerts_seen);
+}
+
+pub async fn run_server() -> std::io::Result<()> {
+    env_logger::init_from_env(Env::default().default_filter_or("info"));
 
+    let config = load_config().map_err(std::io::Error::other)?;
+    let state = try_start(&config).await.map_err(std::io::Error::other)?;
 
     HttpServer::new(move || {
         let cors = Cors::permissive();
         App::new()
-            .app_data(web::Data::new(AppState {
-                indexer: indexer.clone(),
-                searcher: searcher.clone(),
-                db: connection.clone(),
-            }))
-            .service(root)
-            .service(ingest)
-            .service(search)
+            .app_data(state.clone())
+            .configure(configure)
             .wrap(Logger::default())
             .wrap(Logger::new("%a %{User-Agent}i"))
             .wrap(cors)
@@ -0,0 +1,339 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::embedding::EmbeddingService;
+use crate::explain::SearchTrace;
+use crate::index::{
+    save_metadata, store_path, AllowedDocuments, DocumentKeys, IndexMetadata, INDEX_NAME,
+};
+use crate::query_cache::{QueryCache, QueryCacheStats};
+use crate::store::{
+    cosine_distance, discard_delta, has_delta, try_open_store, try_view_store, view_store, Hit,
+    SearchOptions, StoreStats, VectorStore,
+};
+
+/// Where the query cache is saved, if persisted.
+const QUERY_CACHE_FILE: &str = "query_cache.json";
+
+/// An index last used memory-mapped stays mapped until its delta is merged.
+fn open_live_store(
+    config: &Config,
+    metadata: &IndexMetadata,
+    path: &str,
+) -> Result<Box<dyn VectorStore>, String> {
+    if config.index.memory_mapped || has_delta(path) {
+        try_view_store(metadata, path)
+    } else {
+        try_open_store(metadata, path)
+    }
+}
+
+struct Live {
+    metadata: IndexMetadata,
+    embeddings: EmbeddingService,
+    store: Box<dyn VectorStore>,
+}
+
+/// The index being served, shared by the search workers and the indexer.
+/// Searches hold the read lock, so any number run in parallel; the indexer
+/// takes the write lock only while adding a batch or swapping in a rebuilt
+/// index, and saves to disk under the read lock.
+#[derive(Clone)]
+pub struct LiveIndex {
+    config: Config,
+    live: Arc<RwLock<Live>>,
+    /// Set while the indexer builds a replacement for the live index.
+    rebuilding: Arc<AtomicBool>,
+    query_cache: Arc<QueryCache>,
+}
+
+impl LiveIndex {
+    /// Opens the existing index with the model it was built with, which may
+    /// differ from the configured model while a migration is running. Fails
+    /// on an index that does not match its model or cannot be read.
+    pub fn open(
+        config: &Config,
+        embeddings: &EmbeddingService,
+        metadata: &IndexMetadata,
+    ) -> Result<LiveIndex, String> {
+        let repair = "; run `semtex-api doctor --repair` to rebuild it";
+        if embeddings.dimensions() != metadata.dimensions {
+            return Err(format!(
+                "{} produces {} dimensions but the index has {}{}",
+                metadata.model,
+                embeddings.dimensions(),
+                metadata.dimensions,
+                repair
+            ));
+        }
+
+        let path = store_path(config, metadata, INDEX_NAME);
+        let store = open_live_store(config, metadata, &path).map_err(|e| e + repair)?;
+        save_metadata(config, metadata);
+        let query_cache = QueryCache::new(
+            config.query_cache.capacity,
+            config
+                .query_cache
+                .persist
+                .then(|| config.data_path(QUERY_CACHE_FILE)),
+            &metadata.model,
+        );
+
+        Ok(LiveIndex {
+            config: config.clone(),
+            live: Arc::new(RwLock::new(Live {
+                metadata: metadata.clone(),
+                embeddings: embeddings.clone(),
+                store,
+            })),
+            rebuilding: Arc::new(AtomicBool::new(false)),
+            query_cache: Arc::new(query_cache),
+        })
+    }
+
+    pub fn metadata(&self) -> IndexMetadata {
+        self.live.read().unwrap().metadata.clone()
+    }
+
+    /// The stored vector for `key`, if the live index has one.
+    pub fn get(&self, key: u64) -> Option<Vec<f32>> {
+        self.live.read().unwrap().store.get(key)
+    }
+
+    /// Embeds `query` and returns what keys refer to, the `count` nearest
+    /// keys among `allowed`, if `with_vectors` their vectors, and how long
+    /// it took. The query is embedded outside the lock, unless cached; should
+    /// a migration swap models meanwhile, it is embedded again with the new
+    /// one.
+    pub fn search(
+        &self,
+        query: &str,
+        count: usize,
+        expansion_search: Option<usize>,
+        allowed: Option<&AllowedDocuments>,
+        with_vectors: bool,
+    ) -> (DocumentKeys, Vec<Hit>, Vec<Vec<f32>>, SearchTrace) {
+        self.search_embedded(
+            count,
+            expansion_search,
+            allowed,
+            with_vectors,
+            |embeddings, model, _| (self.embed_query(embeddings, model, query), None),
+        )
+    }
+
+    /// The `count` documents among `allowed` nearest to the content row with
+    /// stable `key` and row `id`, leaving the row itself out. Its stored vector is the
+    /// query; a row the index has no vector for is embedded again from
+    /// `title` and `text`.
+    pub fn similar(
+        &self,
+        (key, id): (u64, u64),
+        (title, text): (&str, &str),
+        count: usize,
+        expansion_search: Option<usize>,
+        allowed: Option<&AllowedDocuments>,
+    ) -> (DocumentKeys, Vec<Hit>) {
+        let (keys, hits, _, _) = self.search_embedded(
+            count,
+            expansion_search,
+            allowed,
+            false,
+            |embeddings, _, keys| {
+                let key = match keys {
+                    DocumentKeys::Stable => key,
+                    DocumentKeys::RowId => id,
+                };
+                let vector = match self.get(key) {
+                    Some(vector) => vector,
+                    None => embeddings.embed_documents(&[(title, text)]).remove(0),
+                };
+                (vector, Some(key))
+            },
+        );
+        (keys, hits)
+    }
+
+    /// Searches with the vector `embed` makes, outside the lock, from the
+    /// live embeddings, model id and key kind, leaving out the key it returns
+    /// alongside.
+    fn search_embedded(
+        &self,
+        count: usize,
+        expansion_search: Option<usize>,
+        allowed: Option<&AllowedDocuments>,
+        with_vectors: bool,
+        embed: impl Fn(&EmbeddingService, &str, DocumentKeys) -> (Vec<f32>, Option<u64>),
+    ) -> (DocumentKeys, Vec<Hit>, Vec<Vec<f32>>, SearchTrace) {
+        let mut embed_time = Duration::ZERO;
+        loop {
+            let (model, keys, embeddings) = {
+                let live = self.live.read().unwrap();
+                (
+                    live.metadata.model.clone(),
+                    live.metadata.keys,
+                    live.embeddings.clone(),
+                )
+            };
+            let started = Instant::now();
+            let (vector, exclude) = embed(&embeddings, &model, keys);
+            embed_time += started.elapsed();
+            let filter = |key: u64| {
+                Some(key) != exclude
+                    && allowed
+                        .map(|allowed| allowed.contains(keys, key))
+                        .unwrap_or(true)
+            };
+
+            let live = self.live.read().unwrap();
+            if live.metadata.model == model && live.metadata.keys == keys {
+                let started = Instant::now();
+                let hits = live.store.search(
+                    &vector,
+                    &SearchOptions {
+                        expansion_search: expansion_search
+                            .or(Some(self.config.index.expansion_search)),
+                        filter: (exclude.is_some() || allowed.is_some())
+                            .then_some(&filter as &dyn Fn(u64) -> bool),
+                        ..SearchOptions::top(count)
+                    },
+                );
+                let vectors = if with_vectors {
+                    hits.iter()
+                        .map(|hit| live.store.get(hit.key).unwrap())
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                let trace = SearchTrace {
+                    embed: embed_time,
+                    search: started.elapsed(),
+                    query_norm: vector.iter().map(|x| x * x).sum::<f32>().sqrt(),
+                };
+                return (keys, hits, vectors, trace);
+            }
+        }
+    }
+
+    /// Similarity of each of `passages` to `query` under the live model;
+    /// higher is more similar.
+    pub fn similarities(&self, query: &str, passages: &[&str]) -> Vec<f32> {
+        let (model, embeddings) = {
+            let live = self.live.read().unwrap();
+            (live.metadata.model.clone(), live.embeddings.clone())
+        };
+        let query = self.embed_query(&embeddings, &model, query);
+        embeddings
+            .embed_passages(passages)
+            .iter()
+            .map(|passage| 1.0 - cosine_distance(&query, passage))
+            .collect()
+    }
+
+    /// The embedding of `query` under the live model, leaving the query cache
+    /// to searches.
+    pub fn query_vector(&self, query: &str) -> Vec<f32> {
+        let embeddings = self.live.read().unwrap().embeddings.clone();
+        embeddings.embed_query(query)
+    }
+
+    /// The embedding of `query` under `model`, from the cache if it is there.
+    fn embed_query(&self, embeddings: &EmbeddingService, model: &str, query: &str) -> Vec<f32> {
+        if let Some(vector) = self.query_cache.get(model, query) {
+            return vector;
+        }
+        let vector = embeddings.embed_query(query);
+        self.query_cache.insert(model, query, &vector);
+        vector
+    }
+
+    pub fn query_cache_stats(&self) -> QueryCacheStats {
+        self.query_cache.stats()
+    }
+
+    /// Adds embedded documents and saves the index.
+    pub fn add(&self, entries: &[(u64, Vec<f32>)]) {
+        {
+            let mut live = self.live.write().unwrap();
+            for (key, vector) in entries.iter() {
+                live.store.add(*key, vector);
+            }
+        }
+
+        let live = self.live.read().unwrap();
+        live.store
+            .persist(&store_path(&self.config, &live.metadata, INDEX_NAME));
+    }
+
+    /// Removes the vectors stored under `keys` and saves the index.
+    pub fn remove(&self, keys: &[u64]) {
+        {
+            let mut live = self.live.write().unwrap();
+            for key in keys.iter() {
+                live.store.remove(*key);
+            }
+        }
+
+        let live = self.live.read().unwrap();
+        live.store
+            .persist(&store_path(&self.config, &live.metadata, INDEX_NAME));
+    }
+
+    /// Replaces the index and query model with a rebuilt index that has
+    /// already been saved to `saved_path`. Searches wait only for the file to
+    /// be renamed into place, and mapped if the index is memory-mapped.
+    pub fn swap(
+        &self,
+        metadata: IndexMetadata,
+        store: Box<dyn VectorStore>,
+        embeddings: EmbeddingService,
+        saved_path: &str,
+    ) {
+        let mut live = self.live.write().unwrap();
+        let old_path = store_path(&self.config, &live.metadata, INDEX_NAME);
+        let path = store_path(&self.config, &metadata, INDEX_NAME);
+
+        std::fs::rename(saved_path, &path).unwrap();
+        save_metadata(&self.config, &metadata);
+
+        // The rebuilt index includes the old delta.
+        discard_delta(&old_path);
+        let store = if self.config.index.memory_mapped {
+            drop(store);
+            view_store(&metadata, &path)
+        } else {
+            store
+        };
+
+        if live.metadata.model != metadata.model {
+            self.query_cache.retain_model(&metadata.model);
+        }
+        *live = Live {
+            metadata,
+            embeddings,
+            store,
+        };
+
+        // A different backend leaves the old index file behind.
+        if old_path != path {
+            let _ = std::fs::remove_file(old_path);
+        }
+    }
+
+    /// Whether a migration or compaction is building a replacement index.
+    pub fn is_rebuilding(&self) -> bool {
+        self.rebuilding.load(Ordering::Relaxed)
+    }
+
+    pub fn set_rebuilding(&self, rebuilding: bool) {
+        self.rebuilding.store(rebuilding, Ordering::Relaxed);
+    }
+
+    /// The model serving queries and the state of the index.
+    pub fn stats(&self) -> (String, StoreStats) {
+        let live = self.live.read().unwrap();
+        (live.metadata.model.clone(), live.store.stats())
+    }
+}
@@ -1,7 +1,16 @@
-use semtex_api::run_server;
+use env_logger::Env;
+use semtex_api::{doctor, run_server};
 
 #[actix_web::main]
 pub async fn main() -> std::io::Result<()> {
-    run_server().await
-}
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
 
+    match args.first().map(|arg| arg.as_str()) {
+        Some("doctor") => {
+            env_logger::init_from_env(Env::default().default_filter_or("warn"));
+            doctor::run(args.iter().any(|arg| arg == "--repair")).await;
+            Ok(())
+        }
+        _ => run_server().await,
+    }
+}
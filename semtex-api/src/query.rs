@@ -0,0 +1,289 @@
+//! The search box query language. Besides free text, which is embedded, a
+//! query may hold:
+//!
+//! ```text
+//! site:github.com      pages on github.com or its subdomains
+//! source:notes         pages stored from the `notes` source
+//! after:2024-01-01     pages stored on or after a date: YYYY, YYYY-MM,
+//! before:"last week"   YYYY-MM-DD or a natural date, which may be quoted
+//! "exact phrase"       pages containing the phrase
+//! -word, -"a phrase"   pages not containing the word or phrase
+//! ```
+//!
+//! Natural dates (`today`, `yesterday`, `this week`, `last month`, `past 3
+//! days`, ...) in the free text restrict results to that period. Weeks start
+//! on Monday and days in UTC.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Query {
+    /// The words left once operators are taken out, which are embedded.
+    pub text: String,
+    pub sites: Vec<String>,
+    pub sources: Vec<String>,
+    /// Only pages stored at or after this time.
+    pub after: Option<DateTime<Utc>>,
+    /// Only pages stored before this time.
+    pub before: Option<DateTime<Utc>>,
+    pub phrases: Vec<String>,
+    pub excluded: Vec<String>,
+}
+
+enum Token {
+    Word(String),
+    Phrase(String),
+    Exclude(String),
+    Operator(String, String),
+}
+
+impl Query {
+    pub fn parse(input: &str, now: DateTime<Utc>) -> Query {
+        let mut query = Query::default();
+        let mut words = Vec::new();
+
+        for token in tokens(input) {
+            match token {
+                Token::Word(word) => words.push(word),
+                Token::Phrase(phrase) => query.phrases.push(phrase),
+                Token::Exclude(term) => query.excluded.push(term),
+                Token::Operator(name, value) => {
+                    if !query.apply(&name, &value, now) {
+                        words.push(format!("{}:{}", name, value));
+                    }
+                }
+            }
+        }
+
+        query.text = query.take_dates(&words, now).join(" ");
+        query
+    }
+
+    /// Whether anything besides the free text restricts the results.
+    pub fn has_filters(&self) -> bool {
+        !self.sites.is_empty()
+            || !self.sources.is_empty()
+            || self.after.is_some()
+            || self.before.is_some()
+            || !self.phrases.is_empty()
+            || !self.excluded.is_empty()
+    }
+
+    /// Each operator of the query on its own, as it would be written.
+    pub fn operators(&self) -> Vec<(String, Query)> {
+        let date = |time: &DateTime<Utc>| time.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+
+        let mut operators = Vec::new();
+        for site in self.sites.iter() {
+            let query = Query {
+                sites: vec![site.clone()],
+                ..Query::default()
+            };
+            operators.push((format!("site:{}", site), query));
+        }
+        for source in self.sources.iter() {
+            let query = Query {
+                sources: vec![source.clone()],
+                ..Query::default()
+            };
+            operators.push((format!("source:{}", source), query));
+        }
+        if let Some(after) = self.after {
+            let query = Query {
+                after: Some(after),
+                ..Query::default()
+            };
+            operators.push((format!("after:{}", date(&after)), query));
+        }
+        if let Some(before) = self.before {
+            let query = Query {
+                before: Some(before),
+                ..Query::default()
+            };
+            operators.push((format!("before:{}", date(&before)), query));
+        }
+        for phrase in self.phrases.iter() {
+            let query = Query {
+                phrases: vec![phrase.clone()],
+                ..Query::default()
+            };
+            operators.push((format!("\"{}\"", phrase), query));
+        }
+        for term in self.excluded.iter() {
+            let query = Query {
+                excluded: vec![term.clone()],
+                ..Query::default()
+            };
+            let term = if term.contains(' ') {
+                format!("\"{}\"", term)
+            } else {
+                term.clone()
+            };
+            operators.push((format!("-{}", term), query));
+        }
+        operators
+    }
+
+    /// Applies `name:value`, or returns false if it is not an operator.
+    fn apply(&mut self, name: &str, value: &str, now: DateTime<Utc>) -> bool {
+        match name.to_lowercase().as_str() {
+            "site" => self.sites.push(value.to_lowercase()),
+            "source" => self.sources.push(value.to_string()),
+            "after" => match date_value(value, now) {
+                Some(start) => self.restrict(Some(start), None),
+                None => return false,
+            },
+            "before" => match date_value(value, now) {
+                Some(start) => self.restrict(None, Some(start)),
+                None => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    /// Narrows the period results must be stored in.
+    fn restrict(&mut self, after: Option<DateTime<Utc>>, before: Option<DateTime<Utc>>) {
+        if let Some(after) = after {
+            self.after = Some(self.after.map_or(after, |a| a.max(after)));
+        }
+        if let Some(before) = before {
+            self.before = Some(self.before.map_or(before, |b| b.min(before)));
+        }
+    }
+
+    /// Takes natural dates out of `words`, restricting the period to them,
+    /// and returns the remaining words.
+    fn take_dates(&mut self, words: &[String], now: DateTime<Utc>) -> Vec<String> {
+        let mut rest = Vec::new();
+        let mut i = 0;
+        while i < words.len() {
+            let found = (1..=3).rev().find_map(|n| {
+                let phrase = words.get(i..i + n)?.join(" ").to_lowercase();
+                natural_date(&phrase, now).map(|period| (n, period))
+            });
+            match found {
+                Some((n, (start, end))) => {
+                    self.restrict(Some(start), Some(end));
+                    i += n;
+                }
+                None => {
+                    rest.push(words[i].clone());
+                    i += 1;
+                }
+            }
+        }
+        rest
+    }
+}
+
+fn tokens(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let excluded = c == '-';
+        if excluded {
+            chars.next();
+        }
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            let phrase = chars.by_ref().take_while(|&c| c != '"').collect::<String>();
+            let phrase = phrase.trim().to_string();
+            if phrase.is_empty() {
+                continue;
+            }
+            tokens.push(if excluded {
+                Token::Exclude(phrase)
+            } else {
+                Token::Phrase(phrase)
+            });
+            continue;
+        }
+
+        let mut word = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            chars.next();
+            // A quoted operator value, as in `after:"last week"`.
+            if c == ':' && chars.peek() == Some(&'"') && !word.is_empty() {
+                chars.next();
+                let value = chars.by_ref().take_while(|&c| c != '"').collect::<String>();
+                word = format!("{}:{}", word, value.trim());
+                break;
+            }
+            word.push(c);
+        }
+
+        tokens.push(match (excluded, word.split_once(':')) {
+            (true, _) if !word.is_empty() => Token::Exclude(word),
+            (true, _) => Token::Word("-".to_string()),
+            (false, Some((name, value))) if !name.is_empty() && !value.is_empty() => {
+                Token::Operator(name.to_string(), value.to_string())
+            }
+            _ => Token::Word(word),
+        });
+    }
+    tokens
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0).unwrap().and_utc()
+}
+
+/// Where the period an operator value names starts: a date, month or year,
+/// or a natural date.
+fn date_value(value: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let numbers = value
+        .split('-')
+        .map(|part| part.parse::<u32>().ok())
+        .collect::<Option<Vec<_>>>();
+    let start = match numbers.as_deref() {
+        Some([year, month, day]) => NaiveDate::from_ymd_opt(*year as i32, *month, *day),
+        Some([year, month]) => NaiveDate::from_ymd_opt(*year as i32, *month, 1),
+        Some([year]) if *year >= 1000 => NaiveDate::from_ymd_opt(*year as i32, 1, 1),
+        _ => return natural_date(&value.to_lowercase(), now).map(|(start, _)| start),
+    }?;
+    Some(midnight(start))
+}
+
+/// The period a natural date such as `last week` names, ending now for the
+/// current day, week, month or year.
+fn natural_date(phrase: &str, now: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let today = now.date_naive();
+    let week = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let month = today.with_day(1).unwrap();
+    let year = NaiveDate::from_ymd_opt(today.year(), 1, 1).unwrap();
+    let previous_month = (month - Duration::days(1)).with_day(1).unwrap();
+    let previous_year = NaiveDate::from_ymd_opt(today.year() - 1, 1, 1).unwrap();
+
+    let words = phrase.split(' ').collect::<Vec<_>>();
+    let (start, end) = match words.as_slice() {
+        ["today"] => (midnight(today), now),
+        ["yesterday"] => (midnight(today - Duration::days(1)), midnight(today)),
+        ["this", "week"] => (midnight(week), now),
+        ["this", "month"] => (midnight(month), now),
+        ["this", "year"] => (midnight(year), now),
+        ["last", "week"] => (midnight(week - Duration::days(7)), midnight(week)),
+        ["last", "month"] => (midnight(previous_month), midnight(month)),
+        ["last", "year"] => (midnight(previous_year), midnight(year)),
+        ["past" | "last", count, unit] => {
+            let count = count.parse::<i64>().ok().filter(|count| *count >= 0)?;
+            let days = match *unit {
+                "day" | "days" => count,
+                "week" | "weeks" => count.checked_mul(7)?,
+                _ => return None,
+            };
+            (now.checked_sub_signed(Duration::try_days(days)?)?, now)
+        }
+        _ => return None,
+    };
+    Some((start, end))
+}
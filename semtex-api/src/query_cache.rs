@@ -0,0 +1,201 @@
+//! Embeddings of recent queries, so that repeating a search, or the searches
+//! the search box sends while a query is being typed, does not run the model
+//! again. Entries are keyed by model, so a vector is never used with an index
+//! built by another model.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+/// New entries after which a persisted cache is saved again.
+const SAVE_EVERY: usize = 16;
+
+type Key = (String, String);
+
+#[derive(Default)]
+struct Cache {
+    /// Vectors by model and query, with when they were last used.
+    entries: HashMap<Key, (Vec<f32>, u64)>,
+    /// Keys by when they were last used, least recently first.
+    order: BTreeMap<u64, Key>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+    /// Entries added since the cache was last saved.
+    unsaved: usize,
+}
+
+impl Cache {
+    fn touch(&mut self, key: &Key) {
+        self.clock += 1;
+        let (_, used) = self.entries.get_mut(key).unwrap();
+        self.order.remove(used);
+        *used = self.clock;
+        self.order.insert(self.clock, key.clone());
+    }
+
+    fn insert(&mut self, key: Key, vector: Vec<f32>, capacity: usize) {
+        if let Some((_, used)) = self.entries.remove(&key) {
+            self.order.remove(&used);
+        }
+        while self.entries.len() >= capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+        self.entries.insert(key.clone(), (vector, 0));
+        self.touch(&key);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct SavedEntry {
+    model: String,
+    query: String,
+    vector: Vec<f32>,
+}
+
+/// How well the cache is doing since the server started.
+#[derive(Clone, Debug, Serialize)]
+pub struct QueryCacheStats {
+    pub capacity: usize,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// Fraction of lookups that were hits, 0 before the first lookup.
+    pub hit_rate: f32,
+}
+
+/// A bounded, least recently used cache of query embeddings.
+pub struct QueryCache {
+    capacity: usize,
+    /// Where the cache is saved, if it is persisted.
+    path: Option<String>,
+    cache: Mutex<Cache>,
+}
+
+impl QueryCache {
+    /// A cache of up to `capacity` vectors, none if 0. If `path` is given the
+    /// cache is saved there, and the entries for `model` saved earlier are
+    /// loaded from it.
+    pub fn new(capacity: usize, path: Option<String>, model: &str) -> QueryCache {
+        let mut cache = Cache::default();
+        let saved = path
+            .as_ref()
+            .filter(|path| capacity > 0 && Path::new(path).exists());
+        if let Some(path) = saved {
+            let saved: Vec<SavedEntry> =
+                serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+            for entry in saved.into_iter().filter(|entry| entry.model == model) {
+                cache.insert((entry.model, entry.query), entry.vector, capacity);
+            }
+        }
+
+        QueryCache {
+            capacity,
+            path,
+            cache: Mutex::new(cache),
+        }
+    }
+
+    /// The vector of `query` under `model`, if cached.
+    pub fn get(&self, model: &str, query: &str) -> Option<Vec<f32>> {
+        let mut cache = self.cache.lock().unwrap();
+        let key = (model.to_string(), query.to_string());
+        if cache.entries.contains_key(&key) {
+            cache.hits += 1;
+            cache.touch(&key);
+            Some(cache.entries[&key].0.clone())
+        } else {
+            cache.misses += 1;
+            None
+        }
+    }
+
+    /// Caches `vector` as the embedding of `query` under `model`, evicting
+    /// the least recently used entry if the cache is full.
+    pub fn insert(&self, model: &str, query: &str, vector: &[f32]) {
+        if self.capacity == 0 {
+            return;
+        }
+        let save = {
+            let mut cache = self.cache.lock().unwrap();
+            cache.insert(
+                (model.to_string(), query.to_string()),
+                vector.to_vec(),
+                self.capacity,
+            );
+            cache.unsaved += 1;
+            cache.unsaved >= SAVE_EVERY
+        };
+        if save {
+            self.save();
+        }
+    }
+
+    /// Drops the entries of models other than `model`, once it has replaced
+    /// them.
+    pub fn retain_model(&self, model: &str) {
+        {
+            let mut cache = self.cache.lock().unwrap();
+            cache.entries.retain(|(m, _), _| m == model);
+            cache.order.retain(|_, (m, _)| m == model);
+        }
+        self.save();
+    }
+
+    /// Saves the cache, if it is persisted.
+    pub fn save(&self) {
+        self.write().unwrap();
+    }
+
+    fn write(&self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let saved = {
+            let mut cache = self.cache.lock().unwrap();
+            cache.unsaved = 0;
+            cache
+                .order
+                .values()
+                .map(|key| SavedEntry {
+                    model: key.0.clone(),
+                    query: key.1.clone(),
+                    vector: cache.entries[key].0.clone(),
+                })
+                .collect::<Vec<_>>()
+        };
+        std::fs::write(path, serde_json::to_string(&saved).unwrap())
+    }
+
+    pub fn stats(&self) -> QueryCacheStats {
+        let cache = self.cache.lock().unwrap();
+        let lookups = cache.hits + cache.misses;
+        QueryCacheStats {
+            capacity: self.capacity,
+            entries: cache.entries.len(),
+            hits: cache.hits,
+            misses: cache.misses,
+            hit_rate: if lookups > 0 {
+                cache.hits as f32 / lookups as f32
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+impl Drop for QueryCache {
+    /// Saves what was added since the last save. The data directory may be
+    /// gone by now, so failing to is not an error.
+    fn drop(&mut self) {
+        let unsaved = self.cache.lock().map(|cache| cache.unsaved).unwrap_or(0);
+        if unsaved > 0 {
+            let _ = self.write();
+        }
+    }
+}
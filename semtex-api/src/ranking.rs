@@ -0,0 +1,59 @@
+//! Blends similarity to the query with how recently and how often a page was
+//! visited, and how much its source is trusted.
+
+use serde::Serialize;
+
+use crate::config::RankingConfig;
+
+/// What is known about a result besides its similarity to the query.
+pub struct Signals<'a> {
+    /// Similarity to the query, from 0 to 1.
+    pub similarity: f32,
+    /// Days since the page was last visited.
+    pub age_days: f32,
+    /// Times the page was stored, once per visit.
+    pub visits: u32,
+    pub source: &'a str,
+}
+
+/// The parts of a result's ranking score, for tuning the weights.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Score {
+    /// Weighted similarity to the query.
+    pub similarity: f32,
+    /// Weighted recency, halving every `half_life_days`.
+    pub recency: f32,
+    /// Weighted logarithm of the visit count.
+    pub visits: f32,
+    /// What the sum of the above is multiplied by for the page's source.
+    pub source_weight: f32,
+    pub total: f32,
+    pub age_days: f32,
+    pub visit_count: u32,
+}
+
+pub fn score(config: &RankingConfig, signals: &Signals) -> Score {
+    let similarity = config.similarity_weight * signals.similarity;
+    let recency = if config.half_life_days > 0.0 {
+        let half_lives = signals.age_days.max(0.0) / config.half_life_days;
+        config.recency_weight * 0.5f32.powf(half_lives)
+    } else {
+        0.0
+    };
+    let visits = config.visits_weight * (signals.visits as f32).ln_1p();
+    let source_weight = config
+        .source_weights
+        .get(signals.source)
+        .copied()
+        .unwrap_or(1.0);
+
+    Score {
+        similarity,
+        recency,
+        visits,
+        source_weight,
+        total: (similarity + recency + visits) * source_weight,
+        age_days: signals.age_days,
+        visit_count: signals.visits,
+    }
+}
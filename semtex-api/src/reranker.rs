@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use actix::dev::{MessageResponse, OneshotSender};
+use actix::prelude::*;
+use semtex_vector::cross_encoder::Reranker;
+
+#[derive(Message)]
+#[rtype(result = "RerankResponse")]
+pub enum RerankMessage {
+    /// Scores `passages` against `query` in order, until `deadline`.
+    Rerank {
+        query: String,
+        passages: Vec<String>,
+        deadline: Instant,
+    },
+}
+
+#[derive(Debug)]
+pub enum RerankResponse {
+    /// One score per passage; `None` for those not scored before the deadline.
+    Scores { scores: Vec<Option<f32>> },
+}
+
+impl<A, M> MessageResponse<A, M> for RerankResponse
+where
+    A: Actor,
+    M: Message<Result = RerankResponse>,
+{
+    fn handle(self, _ctx: &mut A::Context, tx: Option<OneshotSender<M::Result>>) {
+        if let Some(tx) = tx {
+            tx.send(self).unwrap();
+        }
+    }
+}
+
+pub struct RerankerActor {
+    reranker: Arc<dyn Reranker>,
+}
+
+impl Actor for RerankerActor {
+    type Context = SyncContext<Self>;
+}
+
+pub fn reranker(reranker: &Arc<dyn Reranker>) -> RerankerActor {
+    RerankerActor {
+        reranker: reranker.clone(),
+    }
+}
+
+impl Handler<RerankMessage> for RerankerActor {
+    type Result = RerankResponse;
+
+    fn handle(&mut self, msg: RerankMessage, _ctx: &mut SyncContext<Self>) -> Self::Result {
+        match msg {
+            RerankMessage::Rerank {
+                query,
+                passages,
+                deadline,
+            } => {
+                // Passages are left unscored once the deadline passes or the
+                // model fails, and keep their order by vector distance.
+                let mut failed = false;
+                let scores = passages
+                    .iter()
+                    .map(|passage| {
+                        if failed || Instant::now() >= deadline {
+                            return None;
+                        }
+                        match self.reranker.score(&query, passage) {
+                            Ok(score) if !score.is_nan() => Some(score),
+                            Ok(_) => {
+                                log::warn!("{} scored a passage NaN", self.reranker.model_id());
+                                failed = true;
+                                None
+                            }
+                            Err(e) => {
+                                log::warn!(
+                                    "reranking with {} failed: {}",
+                                    self.reranker.model_id(),
+                                    e
+                                );
+                                failed = true;
+                                None
+                            }
+                        }
+                    })
+                    .collect();
+                RerankResponse::Scores { scores }
+            }
+        }
+    }
+}
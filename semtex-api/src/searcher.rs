@@ -1,35 +1,62 @@
-use std::borrow::Borrow;
-use std::cmp::{min, max};
-
 use actix::dev::{MessageResponse, OneshotSender};
 use actix::prelude::*;
-use semtex_vector::embed;
-use semtex_vector::jina_candle::JinaCandle;
-use semtex_vector::minilm::MiniLM;
-use usearch::{new_index, Index};
-
-use usearch::ffi::{IndexOptions, MetricKind, ScalarKind};
 
-use crate::Models;
-use crate::util::xdg_dirs;
+use crate::explain::SearchTrace;
+use crate::index::{AllowedDocuments, DocumentKeys};
+use crate::live::LiveIndex;
+use crate::query_cache::QueryCacheStats;
+use crate::store::{Hit, StoreStats};
 
 #[derive(Message)]
 #[rtype(result = "SearchResponse")]
 pub enum SearchMessage {
-    Search { query: String },
-    Index { key: u64, vector: Vec<f32> },
-}
-
-#[derive(Debug)]
-pub struct SearchResult {
-    pub key: u64,
-    pub distance: f32,
+    Search {
+        query: String,
+        count: usize,
+        /// Overrides the configured `expansion_search` for this query.
+        expansion_search: Option<usize>,
+        /// The only rows results may come from, if restricted.
+        allowed: Option<AllowedDocuments>,
+        /// Whether to return the vectors of the hits, for diversifying.
+        with_vectors: bool,
+    },
+    /// Content nearest to a stored row, other than the row itself.
+    Similar {
+        key: u64,
+        id: u64,
+        title: String,
+        text: String,
+        count: usize,
+        expansion_search: Option<usize>,
+        allowed: Option<AllowedDocuments>,
+    },
+    /// How similar each passage is to the query, for choosing snippets.
+    Similarities {
+        query: String,
+        passages: Vec<String>,
+    },
+    Stats,
 }
 
 #[derive(Debug)]
 pub enum SearchResponse {
-    SearchResult { results: Vec<SearchResult> },
-    IndexResult,
+    SearchResult {
+        /// What the hit keys refer to.
+        keys: DocumentKeys,
+        results: Vec<Hit>,
+        /// The vectors of `results` in order, if asked for.
+        vectors: Vec<Vec<f32>>,
+        trace: SearchTrace,
+    },
+    Similarities {
+        similarities: Vec<f32>,
+    },
+    Stats {
+        model: String,
+        stats: StoreStats,
+        rebuilding: bool,
+        query_cache: QueryCacheStats,
+    },
 }
 
 impl<A, M> MessageResponse<A, M> for SearchResponse
@@ -45,83 +72,89 @@ where
 }
 
 pub struct SearcherActor {
-    models: Models,
-    minilm: MiniLM,
-    index: Index,
+    live: LiveIndex,
 }
 
 impl Actor for SearcherActor {
     type Context = SyncContext<Self>;
 
     fn started(&mut self, _ctx: &mut SyncContext<Self>) {
-        println!("Actor is alive");
+        log::info!("Actor is alive");
     }
 
     fn stopped(&mut self, _ctx: &mut SyncContext<Self>) {
-        println!("Actor is stopped");
+        log::info!("Actor is stopped");
     }
 }
 
-
-pub fn searcher(models: &Models) -> SearcherActor {
-    let options = IndexOptions {
-        multi: false,
-        dimensions: 384, //512, //768,
-        metric: MetricKind::Cos,
-        quantization: ScalarKind::F32,
-        connectivity: 0,
-        expansion_add: 0,
-        expansion_search: 0,
-    };
-
-    let index = new_index(&options).unwrap();
-    let index_path = xdg_dirs().place_data_file("index.usearch").unwrap().into_os_string().into_string().unwrap();
-
-    match index.load(&index_path) {
-        Err(_) => {
-            index.save(&index_path).unwrap();
-        }
-        Ok(_) => ()
-    }
-
-    SearcherActor {
-        models: models.clone(),
-        minilm: MiniLM::new(),
-        index: index,
-    }
+/// One of several search workers reading the shared live index in parallel.
+pub fn searcher(live: &LiveIndex) -> SearcherActor {
+    SearcherActor { live: live.clone() }
 }
 
-
 impl Handler<SearchMessage> for SearcherActor {
     type Result = SearchResponse;
 
     fn handle(&mut self, msg: SearchMessage, _ctx: &mut SyncContext<Self>) -> Self::Result {
         match msg {
-            SearchMessage::Search { query } => {
-                let v = embed(&mut self.minilm, &[&query]);
-                let results = self.index.search(&v[0], 10).unwrap();
-
-                return SearchResponse::SearchResult {
-                    results: results
-                        .keys
-                        .iter()
-                        .zip(results.distances)
-                        .map(|(k, d)| SearchResult {
-                            key: *k,
-                            distance: d,
-                        })
-                        .collect::<Vec<_>>(),
-                };
+            SearchMessage::Search {
+                query,
+                count,
+                expansion_search,
+                allowed,
+                with_vectors,
+            } => {
+                let (keys, results, vectors, trace) = self.live.search(
+                    &query,
+                    count,
+                    expansion_search,
+                    allowed.as_ref(),
+                    with_vectors,
+                );
+                SearchResponse::SearchResult {
+                    keys,
+                    results,
+                    vectors,
+                    trace,
+                }
             }
-            SearchMessage::Index { key, vector } => {
-                if self.index.capacity() <= self.index.size() {
-                    self.index.reserve(max(100, self.index.capacity() * 2)).unwrap();
+            SearchMessage::Similar {
+                key,
+                id,
+                title,
+                text,
+                count,
+                expansion_search,
+                allowed,
+            } => {
+                let (keys, results) = self.live.similar(
+                    (key, id),
+                    (&title, &text),
+                    count,
+                    expansion_search,
+                    allowed.as_ref(),
+                );
+                SearchResponse::SearchResult {
+                    keys,
+                    results,
+                    vectors: Vec::new(),
+                    trace: SearchTrace::default(),
+                }
+            }
+            SearchMessage::Similarities { query, passages } => {
+                let passages = passages.iter().map(|p| p.as_str()).collect::<Vec<_>>();
+                SearchResponse::Similarities {
+                    similarities: self.live.similarities(&query, &passages),
+                }
+            }
+            SearchMessage::Stats => {
+                let (model, stats) = self.live.stats();
+                SearchResponse::Stats {
+                    model,
+                    stats,
+                    rebuilding: self.live.is_rebuilding(),
+                    query_cache: self.live.query_cache_stats(),
                 }
-
-                self.index.add(key, vector.as_slice()).unwrap();
-                let index_path = xdg_dirs().place_data_file("index.usearch").unwrap().into_os_string().into_string().unwrap();
-                self.index.save(&index_path).unwrap();
-                SearchResponse::IndexResult
             }
         }
     }
@@ -0,0 +1,204 @@
+//! Query-aware snippets: the sentences of a page that best answer a search,
+//! with the query terms in them marked for highlighting.
+
+use std::ops::Range;
+
+use serde::Serialize;
+
+/// Sentences longer than this many characters are cut into pieces.
+const MAX_SENTENCE_CHARS: usize = 300;
+/// What containing every query term adds to a sentence's similarity.
+const LEXICAL_WEIGHT: f32 = 0.5;
+
+/// A passage of a page, located by character offsets so that it can be shown
+/// and highlighted without the full text.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Snippet {
+    pub text: String,
+    /// Characters of the page before `text`.
+    pub offset: usize,
+    /// Query term matches, as character ranges within `text`.
+    pub highlights: Vec<Range<usize>>,
+}
+
+/// Sentences of `text` as byte ranges, without surrounding whitespace. A
+/// sentence ends at `.`, `!` or `?` followed by whitespace, or at a line
+/// break; overlong sentences are cut at a space.
+pub fn sentences(text: &str) -> Vec<Range<usize>> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let ends = match c {
+            '\n' => true,
+            '.' | '!' | '?' => chars
+                .peek()
+                .map(|(_, next)| next.is_whitespace())
+                .unwrap_or(true),
+            _ => false,
+        };
+        if ends {
+            let end = i + c.len_utf8();
+            push_sentence(text, start..end, &mut sentences);
+            start = end;
+        }
+    }
+    push_sentence(text, start..text.len(), &mut sentences);
+    sentences
+}
+
+/// Adds `range` of `text` to `sentences` trimmed, in pieces of at most
+/// `MAX_SENTENCE_CHARS`.
+fn push_sentence(text: &str, range: Range<usize>, sentences: &mut Vec<Range<usize>>) {
+    let leading = |s: &str| s.len() - s.trim_start().len();
+    let mut start = range.start + leading(&text[range.clone()]);
+    let end = range.start + text[range].trim_end().len();
+
+    while start < end {
+        let piece = &text[start..end];
+        let cut = match piece.char_indices().nth(MAX_SENTENCE_CHARS) {
+            None => piece.len(),
+            Some((limit, _)) => piece[..limit]
+                .rfind(char::is_whitespace)
+                .filter(|&i| i > 0)
+                .unwrap_or(limit),
+        };
+        sentences.push(start..start + piece[..cut].trim_end().len());
+        start += cut + leading(&piece[cut..]);
+    }
+}
+
+/// Byte ranges of the alphanumeric words in `text`.
+fn words(text: &str) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                words.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push(s..text.len());
+    }
+    words
+}
+
+/// Distinct lower-cased words of `query`, the terms highlighted in snippets.
+pub fn terms(query: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for word in words(query) {
+        let term = query[word].to_lowercase();
+        if !terms.contains(&term) {
+            terms.push(term);
+        }
+    }
+    terms
+}
+
+/// Whether `word` matches `term`: equal ignoring case or, for terms of four
+/// or more characters, starting with it, so that "future" finds "futures".
+fn is_match(word: &str, term: &str) -> bool {
+    let word = word.to_lowercase();
+    word == term || (term.chars().count() >= 4 && word.starts_with(term))
+}
+
+/// Byte ranges of the words in `text` matching one of `terms`.
+pub fn matches(text: &str, terms: &[String]) -> Vec<Range<usize>> {
+    words(text)
+        .into_iter()
+        .filter(|word| terms.iter().any(|term| is_match(&text[word.clone()], term)))
+        .collect()
+}
+
+/// Whether `text` contains the words of `phrase` in a row, ignoring case and
+/// punctuation: "go" is found in "Go, fast" but not in "google".
+pub fn contains_words(text: &str, phrase: &str) -> bool {
+    let lowercase = |text: &str| {
+        words(text)
+            .into_iter()
+            .map(|word| text[word].to_lowercase())
+            .collect::<Vec<_>>()
+    };
+    let phrase = lowercase(phrase);
+    !phrase.is_empty()
+        && lowercase(text)
+            .windows(phrase.len())
+            .any(|window| window == phrase)
+}
+
+/// Fraction of `terms` found in `sentence`.
+pub fn lexical_score(sentence: &str, terms: &[String]) -> f32 {
+    if terms.is_empty() {
+        return 0.0;
+    }
+    let words = words(sentence)
+        .into_iter()
+        .map(|word| &sentence[word])
+        .collect::<Vec<_>>();
+    let found = terms
+        .iter()
+        .filter(|term| words.iter().any(|word| is_match(word, term)))
+        .count();
+    found as f32 / terms.len() as f32
+}
+
+/// Up to `count` sentences of `text` with the most query terms, earlier ones
+/// first among equals. Only these are compared with the query by embedding.
+pub fn candidates(text: &str, terms: &[String], count: usize) -> Vec<Range<usize>> {
+    let mut sentences = sentences(text)
+        .into_iter()
+        .map(|sentence| (lexical_score(&text[sentence.clone()], terms), sentence))
+        .collect::<Vec<_>>();
+    sentences.sort_by(|a, b| b.0.total_cmp(&a.0));
+    sentences
+        .into_iter()
+        .take(count)
+        .map(|(_, sentence)| sentence)
+        .collect()
+}
+
+/// The `count` best of `candidates`, each paired with its embedding
+/// similarity to the query (zero if not compared), in page order.
+pub fn snippets(
+    text: &str,
+    terms: &[String],
+    candidates: &[(Range<usize>, f32)],
+    count: usize,
+) -> Vec<Snippet> {
+    let mut scored = candidates
+        .iter()
+        .map(|(sentence, similarity)| {
+            let lexical = lexical_score(&text[sentence.clone()], terms);
+            (similarity + LEXICAL_WEIGHT * lexical, sentence.clone())
+        })
+        .collect::<Vec<_>>();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut best = scored
+        .into_iter()
+        .take(count)
+        .map(|(_, sentence)| sentence)
+        .collect::<Vec<_>>();
+    best.sort_by_key(|sentence| sentence.start);
+    best.into_iter()
+        .map(|sentence| snippet(text, sentence, terms))
+        .collect()
+}
+
+fn snippet(text: &str, sentence: Range<usize>, terms: &[String]) -> Snippet {
+    let passage = &text[sentence.clone()];
+    let chars = |bytes: usize| passage[..bytes].chars().count();
+    Snippet {
+        text: passage.to_string(),
+        offset: text[..sentence.start].chars().count(),
+        highlights: matches(passage, terms)
+            .into_iter()
+            .map(|m| chars(m.start)..chars(m.end))
+            .collect(),
+    }
+}
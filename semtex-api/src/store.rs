@@ -0,0 +1,594 @@
+use std::cmp::max;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+use usearch::ffi::{IndexOptions, MetricKind, ScalarKind};
+use usearch::{new_index, Index};
+
+use crate::index::IndexMetadata;
+
+/// Which `VectorStore` implementation holds the index.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Approximate HNSW search with usearch.
+    #[default]
+    Usearch,
+    /// Exact flat scan over every vector. Slower on large collections, but
+    /// gives ground-truth results.
+    Exact,
+}
+
+impl Backend {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Backend::Usearch => "usearch",
+            Backend::Exact => "exact",
+        }
+    }
+}
+
+/// Scalar type the usearch backend stores vectors as. Smaller types trade a
+/// little recall for memory and speed; the exact backend always uses F32.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorQuantization {
+    #[default]
+    F32,
+    F16,
+    I8,
+}
+
+impl VectorQuantization {
+    fn scalar_kind(&self) -> ScalarKind {
+        match self {
+            VectorQuantization::F32 => ScalarKind::F32,
+            VectorQuantization::F16 => ScalarKind::F16,
+            VectorQuantization::I8 => ScalarKind::I8,
+        }
+    }
+}
+
+pub struct SearchOptions<'a> {
+    pub count: usize,
+    /// Only keys passing the filter are returned.
+    pub filter: Option<&'a dyn Fn(u64) -> bool>,
+    /// Candidate list size for this query, overriding the index default.
+    /// Higher values improve recall at the cost of latency.
+    pub expansion_search: Option<usize>,
+}
+
+impl<'a> SearchOptions<'a> {
+    pub fn top(count: usize) -> SearchOptions<'a> {
+        SearchOptions {
+            count,
+            filter: None,
+            expansion_search: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    pub key: u64,
+    pub distance: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StoreStats {
+    pub backend: Backend,
+    pub quantization: VectorQuantization,
+    pub size: usize,
+    pub dimensions: usize,
+    pub memory_usage: usize,
+    /// Vectors added or removed since a memory-mapped index was last merged.
+    pub delta: usize,
+}
+
+/// Storage and nearest-neighbour search over the document vectors.
+pub trait VectorStore: Send + Sync {
+    /// Adds or replaces the vector stored under `key`.
+    fn add(&mut self, key: u64, vector: &[f32]);
+    /// Returns whether `key` was present.
+    fn remove(&mut self, key: u64) -> bool;
+    fn contains(&self, key: u64) -> bool;
+    fn get(&self, key: u64) -> Option<Vec<f32>>;
+    /// Every key in the store, as far as the backend can enumerate them.
+    fn keys(&self) -> Vec<u64>;
+    /// Returns up to `options.count` nearest vectors, nearest first.
+    /// Distances are cosine distances.
+    fn search(&self, vector: &[f32], options: &SearchOptions) -> Vec<Hit>;
+    /// Writes the store to `path`, from which `open_store` can load it.
+    fn persist(&self, path: &str);
+    fn stats(&self) -> StoreStats;
+}
+
+/// Opens the store at `path` with the backend recorded in `metadata`,
+/// creating it if it does not exist yet. Refuses to open a store whose
+/// contents do not match `metadata`.
+pub fn open_store(metadata: &IndexMetadata, path: &str) -> Box<dyn VectorStore> {
+    try_open_store(metadata, path).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like `open_store`, but reports an unreadable or mismatched store.
+pub fn try_open_store(metadata: &IndexMetadata, path: &str) -> Result<Box<dyn VectorStore>, String> {
+    let store: Box<dyn VectorStore> = match metadata.backend {
+        Backend::Usearch => Box::new(UsearchStore::open(metadata, path)?),
+        Backend::Exact => Box::new(ExactStore::open(metadata, path)?),
+    };
+
+    if !Path::new(path).exists() {
+        store.persist(path);
+    }
+
+    Ok(store)
+}
+
+/// Like `open_store`, but memory-maps a usearch index rather than loading it,
+/// so that only the parts searches touch are resident. Exact stores are
+/// always loaded.
+pub fn view_store(metadata: &IndexMetadata, path: &str) -> Box<dyn VectorStore> {
+    try_view_store(metadata, path).unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub fn try_view_store(metadata: &IndexMetadata, path: &str) -> Result<Box<dyn VectorStore>, String> {
+    match metadata.backend {
+        Backend::Usearch => Ok(Box::new(MappedStore::open(metadata, path)?)),
+        Backend::Exact => try_open_store(metadata, path),
+    }
+}
+
+/// Whether the store at `path` has a delta not yet merged into it.
+pub fn has_delta(path: &str) -> bool {
+    Path::new(&delta_path(path)).exists() || Path::new(&removed_path(path)).exists()
+}
+
+/// Deletes the delta of a memory-mapped store at `path`, once `path` has been
+/// replaced by an index that includes it.
+pub fn discard_delta(path: &str) {
+    let _ = std::fs::remove_file(delta_path(path));
+    let _ = std::fs::remove_file(removed_path(path));
+}
+
+fn passes(filter: Option<&dyn Fn(u64) -> bool>, key: u64) -> bool {
+    match filter {
+        Some(filter) => filter(key),
+        None => true,
+    }
+}
+
+fn check_dimensions(path: &str, dimensions: usize, metadata: &IndexMetadata) -> Result<(), String> {
+    if dimensions != metadata.dimensions {
+        return Err(format!(
+            "{} has {} dimensions but {} expects {}",
+            path, dimensions, metadata.model, metadata.dimensions
+        ));
+    }
+    Ok(())
+}
+
+pub struct UsearchStore {
+    index: Index,
+    quantization: VectorQuantization,
+    /// `expansion_search` is a setting of the whole index, so a search that
+    /// overrides it runs exclusively; all other searches share this lock.
+    expansion: RwLock<()>,
+}
+
+impl UsearchStore {
+    /// Zero leaves a parameter at the usearch default.
+    fn options(metadata: &IndexMetadata) -> IndexOptions {
+        IndexOptions {
+            multi: false,
+            dimensions: metadata.dimensions,
+            metric: MetricKind::Cos,
+            quantization: metadata.quantization.scalar_kind(),
+            connectivity: metadata.connectivity,
+            expansion_add: metadata.expansion_add,
+            expansion_search: 0,
+        }
+    }
+
+    fn open(metadata: &IndexMetadata, path: &str) -> Result<UsearchStore, String> {
+        let index = new_index(&UsearchStore::options(metadata)).unwrap();
+
+        if Path::new(path).exists() {
+            if let Err(e) = index.load(path) {
+                return Err(format!("{} cannot be loaded as {:?}: {}", path, metadata, e));
+            }
+            check_dimensions(path, index.dimensions(), metadata)?;
+        }
+
+        Ok(UsearchStore {
+            index,
+            quantization: metadata.quantization,
+            expansion: RwLock::new(()),
+        })
+    }
+
+    /// Memory-maps the index at `path` read-only.
+    fn view(metadata: &IndexMetadata, path: &str) -> Result<UsearchStore, String> {
+        let index = new_index(&UsearchStore::options(metadata)).unwrap();
+        if let Err(e) = index.view(path) {
+            return Err(format!("{} cannot be viewed as {:?}: {}", path, metadata, e));
+        }
+        check_dimensions(path, index.dimensions(), metadata)?;
+
+        Ok(UsearchStore {
+            index,
+            quantization: metadata.quantization,
+            expansion: RwLock::new(()),
+        })
+    }
+}
+
+impl VectorStore for UsearchStore {
+    fn add(&mut self, key: u64, vector: &[f32]) {
+        if self.index.contains(key) {
+            self.index.remove(key).unwrap();
+        }
+
+        if self.index.capacity() <= self.index.size() {
+            self.index
+                .reserve(max(100, self.index.capacity() * 2))
+                .unwrap();
+        }
+
+        self.index.add(key, vector).unwrap();
+    }
+
+    fn remove(&mut self, key: u64) -> bool {
+        self.index.remove(key).unwrap() > 0
+    }
+
+    fn contains(&self, key: u64) -> bool {
+        self.index.contains(key)
+    }
+
+    fn get(&self, key: u64) -> Option<Vec<f32>> {
+        let mut vector = vec![0.0; self.index.dimensions()];
+        match self.index.get(key, &mut vector).unwrap() {
+            0 => None,
+            _ => Some(vector),
+        }
+    }
+
+    /// usearch cannot list its keys, so this is an exhaustive graph search.
+    /// Nodes unreachable from the entry point are missed.
+    fn keys(&self) -> Vec<u64> {
+        let size = self.index.size();
+        if size == 0 {
+            return Vec::new();
+        }
+
+        let probe = vec![1.0; self.index.dimensions()];
+        self.search(
+            &probe,
+            &SearchOptions {
+                expansion_search: Some(size),
+                ..SearchOptions::top(size)
+            },
+        )
+        .iter()
+        .map(|hit| hit.key)
+        .collect()
+    }
+
+    fn search(&self, vector: &[f32], options: &SearchOptions) -> Vec<Hit> {
+        let count = options.count;
+        let default_expansion = self.index.expansion_search();
+        let expansion = options
+            .expansion_search
+            .filter(|e| *e > 0 && *e != default_expansion);
+
+        let _shared;
+        let _exclusive;
+        match expansion {
+            Some(expansion) => {
+                _exclusive = self.expansion.write().unwrap();
+                self.index.change_expansion_search(expansion);
+            }
+            None => _shared = self.expansion.read().unwrap(),
+        }
+
+        // usearch cannot filter during traversal, so over-fetch until enough
+        // candidates pass the filter or the whole index has been returned.
+        let mut fetch = count;
+        let hits = loop {
+            let matches = self.index.search(vector, fetch).unwrap();
+            let exhausted = matches.keys.len() < fetch || fetch >= self.index.size();
+
+            let hits = matches
+                .keys
+                .iter()
+                .zip(matches.distances.iter())
+                .filter(|(key, _)| passes(options.filter, **key))
+                .take(count)
+                .map(|(key, distance)| Hit {
+                    key: *key,
+                    distance: *distance,
+                })
+                .collect::<Vec<_>>();
+
+            if hits.len() == count || exhausted {
+                break hits;
+            }
+            fetch *= 4;
+        };
+
+        if expansion.is_some() {
+            self.index.change_expansion_search(default_expansion);
+        }
+        hits
+    }
+
+    fn persist(&self, path: &str) {
+        self.index.save(path).unwrap();
+    }
+
+    fn stats(&self) -> StoreStats {
+        StoreStats {
+            backend: Backend::Usearch,
+            quantization: self.quantization,
+            size: self.index.size(),
+            dimensions: self.index.dimensions(),
+            memory_usage: self.index.memory_usage(),
+            delta: 0,
+        }
+    }
+}
+
+fn delta_path(path: &str) -> String {
+    format!("{}.delta", path)
+}
+
+fn removed_path(path: &str) -> String {
+    format!("{}.removed", path)
+}
+
+/// A usearch index memory-mapped read-only, with writes going to a small
+/// in-memory delta index saved as `<path>.delta`. Keys removed from or
+/// replaced in the mapped index are listed in `<path>.removed`. Compacting
+/// the index merges all three into a new mapped index.
+pub struct MappedStore {
+    path: String,
+    base: UsearchStore,
+    delta: UsearchStore,
+    removed: BTreeSet<u64>,
+}
+
+impl MappedStore {
+    fn open(metadata: &IndexMetadata, path: &str) -> Result<MappedStore, String> {
+        if !Path::new(path).exists() {
+            UsearchStore::open(metadata, path)?.persist(path);
+        }
+
+        let removed = match std::fs::read(removed_path(path)) {
+            Ok(bytes) => bytes
+                .chunks_exact(8)
+                .map(|key| u64::from_le_bytes(key.try_into().unwrap()))
+                .collect(),
+            Err(_) => BTreeSet::new(),
+        };
+
+        Ok(MappedStore {
+            path: path.to_string(),
+            base: UsearchStore::view(metadata, path)?,
+            delta: UsearchStore::open(metadata, &delta_path(path))?,
+            removed,
+        })
+    }
+
+    fn in_base(&self, key: u64) -> bool {
+        self.base.contains(key) && !self.removed.contains(&key)
+    }
+}
+
+impl VectorStore for MappedStore {
+    fn add(&mut self, key: u64, vector: &[f32]) {
+        if self.base.contains(key) {
+            self.removed.insert(key);
+        }
+        self.delta.add(key, vector);
+    }
+
+    fn remove(&mut self, key: u64) -> bool {
+        let in_delta = self.delta.remove(key);
+        let in_base = self.base.contains(key) && self.removed.insert(key);
+        in_delta || in_base
+    }
+
+    fn contains(&self, key: u64) -> bool {
+        self.delta.contains(key) || self.in_base(key)
+    }
+
+    fn get(&self, key: u64) -> Option<Vec<f32>> {
+        match self.delta.get(key) {
+            Some(vector) => Some(vector),
+            None if self.in_base(key) => self.base.get(key),
+            None => None,
+        }
+    }
+
+    fn keys(&self) -> Vec<u64> {
+        let mut keys = self.delta.keys();
+        keys.extend(
+            self.base
+                .keys()
+                .into_iter()
+                .filter(|key| !self.removed.contains(key)),
+        );
+        keys
+    }
+
+    fn search(&self, vector: &[f32], options: &SearchOptions) -> Vec<Hit> {
+        let visible = |key: u64| !self.removed.contains(&key) && passes(options.filter, key);
+        let mut hits = self.base.search(
+            vector,
+            &SearchOptions {
+                count: options.count,
+                filter: Some(&visible),
+                expansion_search: options.expansion_search,
+            },
+        );
+        hits.extend(self.delta.search(vector, options));
+
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        hits.truncate(options.count);
+        hits
+    }
+
+    /// Saves the delta and removed keys. The mapped index is read-only, so it
+    /// is only copied when saving somewhere else.
+    fn persist(&self, path: &str) {
+        if path != self.path {
+            std::fs::copy(&self.path, path).unwrap();
+        }
+        self.delta.persist(&delta_path(path));
+
+        let removed = self
+            .removed
+            .iter()
+            .flat_map(|key| key.to_le_bytes())
+            .collect::<Vec<_>>();
+        let tmp = format!("{}.tmp", removed_path(path));
+        std::fs::write(&tmp, removed).unwrap();
+        std::fs::rename(tmp, removed_path(path)).unwrap();
+    }
+
+    fn stats(&self) -> StoreStats {
+        let base = self.base.stats();
+        let delta = self.delta.stats();
+        StoreStats {
+            size: base.size - self.removed.len() + delta.size,
+            memory_usage: base.memory_usage + delta.memory_usage,
+            delta: delta.size + self.removed.len(),
+            ..base
+        }
+    }
+}
+
+/// Keeps every vector in memory and compares the query against all of them.
+/// Stored as a flat file of little-endian `(key, vector)` records after a
+/// dimensions header.
+pub struct ExactStore {
+    dimensions: usize,
+    vectors: BTreeMap<u64, Vec<f32>>,
+}
+
+impl ExactStore {
+    pub fn new(dimensions: usize) -> ExactStore {
+        ExactStore {
+            dimensions,
+            vectors: BTreeMap::new(),
+        }
+    }
+
+    fn open(metadata: &IndexMetadata, path: &str) -> Result<ExactStore, String> {
+        let mut store = ExactStore::new(metadata.dimensions);
+        if !Path::new(path).exists() {
+            return Ok(store);
+        }
+
+        let unreadable = |e: std::io::Error| format!("{} cannot be loaded: {}", path, e);
+        let mut file = BufReader::new(std::fs::File::open(path).map_err(unreadable)?);
+        let mut word = [0u8; 8];
+        file.read_exact(&mut word).map_err(unreadable)?;
+        check_dimensions(path, u64::from_le_bytes(word) as usize, metadata)?;
+
+        let mut scalar = [0u8; 4];
+        while !file.fill_buf().map_err(unreadable)?.is_empty() {
+            file.read_exact(&mut word)
+                .map_err(|e| format!("{} ends in a truncated record: {}", path, e))?;
+            let key = u64::from_le_bytes(word);
+            let mut vector = Vec::with_capacity(store.dimensions);
+            for _ in 0..store.dimensions {
+                file.read_exact(&mut scalar)
+                    .map_err(|e| format!("{} has a truncated vector for {}: {}", path, key, e))?;
+                vector.push(f32::from_le_bytes(scalar));
+            }
+            store.vectors.insert(key, vector);
+        }
+
+        Ok(store)
+    }
+}
+
+pub fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        1.0
+    } else {
+        1.0 - dot / norms
+    }
+}
+
+impl VectorStore for ExactStore {
+    fn add(&mut self, key: u64, vector: &[f32]) {
+        assert_eq!(vector.len(), self.dimensions);
+        self.vectors.insert(key, vector.to_vec());
+    }
+
+    fn remove(&mut self, key: u64) -> bool {
+        self.vectors.remove(&key).is_some()
+    }
+
+    fn contains(&self, key: u64) -> bool {
+        self.vectors.contains_key(&key)
+    }
+
+    fn get(&self, key: u64) -> Option<Vec<f32>> {
+        self.vectors.get(&key).cloned()
+    }
+
+    fn keys(&self) -> Vec<u64> {
+        self.vectors.keys().copied().collect()
+    }
+
+    fn search(&self, vector: &[f32], options: &SearchOptions) -> Vec<Hit> {
+        let mut hits = self
+            .vectors
+            .iter()
+            .filter(|(key, _)| passes(options.filter, **key))
+            .map(|(key, v)| Hit {
+                key: *key,
+                distance: cosine_distance(vector, v),
+            })
+            .collect::<Vec<_>>();
+
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        hits.truncate(options.count);
+        hits
+    }
+
+    fn persist(&self, path: &str) {
+        let tmp = format!("{}.tmp", path);
+        let mut file = BufWriter::new(std::fs::File::create(&tmp).unwrap());
+        file.write_all(&(self.dimensions as u64).to_le_bytes())
+            .unwrap();
+        for (key, vector) in self.vectors.iter() {
+            file.write_all(&key.to_le_bytes()).unwrap();
+            for x in vector {
+                file.write_all(&x.to_le_bytes()).unwrap();
+            }
+        }
+        file.into_inner().unwrap().sync_all().unwrap();
+        std::fs::rename(tmp, path).unwrap();
+    }
+
+    fn stats(&self) -> StoreStats {
+        StoreStats {
+            backend: Backend::Exact,
+            quantization: VectorQuantization::F32,
+            size: self.vectors.len(),
+            dimensions: self.dimensions,
+            memory_usage: self.vectors.len() * (8 + 4 * self.dimensions),
+            delta: 0,
+        }
+    }
+}
@@ -1,6 +1,41 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serializer};
 use xdg::BaseDirectories;
 
 
 pub fn xdg_dirs() -> BaseDirectories {
     xdg::BaseDirectories::with_prefix("semtex").unwrap()
-}
\ No newline at end of file
+}
+
+/// Serializes `value` as a string, for `#[serde(serialize_with)]`.
+pub fn serialize_display<T: Display, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+/// Parses a value serialized as a string, for `#[serde(deserialize_with)]`.
+pub fn deserialize_from_str<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: FromStr,
+    T::Err: Display,
+    D: Deserializer<'de>,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Deserializes a field that is present, even as null, as `Some`, for an
+/// `Option<Option<T>>` with `#[serde(default, deserialize_with)]` that tells
+/// a field set to null from one left out.
+pub fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
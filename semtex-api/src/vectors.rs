@@ -0,0 +1,89 @@
+//! Embeddings kept on their `content` rows, tagged with the model that
+//! produced them. The vector index is derived from these, so it can be rebuilt
+//! or moved to another backend without running the model again.
+
+use entity::content;
+use sea_orm::{
+    ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    TransactionTrait,
+};
+
+use crate::embedding::EmbeddingService;
+
+pub fn encode(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+pub fn decode(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+        .collect()
+}
+
+/// The vector stored on `record`, if it was embedded with `model`.
+pub fn stored_vector(record: &content::Model, model: &str) -> Option<Vec<f32>> {
+    match (&record.embedding, &record.embedding_model) {
+        (Some(blob), Some(embedding_model)) if embedding_model == model => Some(decode(blob)),
+        _ => None,
+    }
+}
+
+/// Stores `vectors`, embedded with `model`, on their content rows.
+pub async fn save_vectors(db: &DatabaseConnection, model: &str, vectors: &[(u64, Vec<f32>)]) {
+    let txn = db.begin().await.unwrap();
+    for (key, vector) in vectors.iter() {
+        save_vector(&txn, model, *key, vector).await;
+    }
+    txn.commit().await.unwrap();
+}
+
+async fn save_vector<C: ConnectionTrait>(db: &C, model: &str, key: u64, vector: &[f32]) {
+    content::Entity::update_many()
+        .set(content::ActiveModel {
+            embedding: ActiveValue::Set(Some(encode(vector))),
+            embedding_model: ActiveValue::Set(Some(model.to_string())),
+            ..Default::default()
+        })
+        .filter(content::Column::Key.eq(key as i64))
+        .exec(db)
+        .await
+        .unwrap();
+}
+
+/// Vectors of `records` under `model`: the stored ones where there are any,
+/// then whatever `fallback` has, and otherwise freshly embedded. Vectors that
+/// were not stored yet are saved, so each row is embedded at most once per
+/// model.
+pub async fn record_vectors(
+    db: &DatabaseConnection,
+    model: &str,
+    embeddings: &EmbeddingService,
+    records: &[content::Model],
+    fallback: impl Fn(u64) -> Option<Vec<f32>>,
+) -> Vec<(u64, Vec<f32>)> {
+    let mut vectors = Vec::new();
+    let mut unsaved = Vec::new();
+    let mut missing = Vec::new();
+
+    for record in records.iter() {
+        let key = record.key as u64;
+        match stored_vector(record, model) {
+            Some(vector) => vectors.push((key, vector)),
+            None => match fallback(key) {
+                Some(vector) => unsaved.push((key, vector)),
+                None => missing.push(record),
+            },
+        }
+    }
+
+    let documents = missing
+        .iter()
+        .map(|r| (r.title.as_str(), r.text.as_str()))
+        .collect::<Vec<_>>();
+    let embedded = embeddings.embed_documents(&documents);
+    unsaved.extend(missing.iter().map(|r| r.key as u64).zip(embedded));
+
+    save_vectors(db, model, &unsaved).await;
+    vectors.extend(unsaved);
+    vectors
+}
@@ -0,0 +1,67 @@
+//! Search history and clicks.
+
+use actix_web::test;
+use serde_json::json;
+use tempfile::TempDir;
+
+use crate::{config, corpus, MODEL};
+
+#[actix_web::test]
+async fn searches_and_clicks_are_recorded_until_history_is_off() {
+    let dir = TempDir::new().unwrap();
+    let config = config(&dir, MODEL);
+    let app = app!(config);
+    ingest!(app, corpus());
+
+    let request = test::TestRequest::get().uri("/search?query=rust%20-bread");
+    let (_, response) = call!(app, request);
+    let search_id = response["search_id"].as_i64().unwrap();
+    let key = response["results"][0]["key"].as_str().unwrap().to_string();
+    // Later pages are not recorded again.
+    let second_page = test::TestRequest::get().uri("/search?query=rust&offset=1");
+    let (_, page) = call!(app, second_page);
+    assert!(page.get("search_id").is_none());
+    assert_eq!(search!(app, "sourdough")[0], "bread");
+
+    let click = test::TestRequest::post()
+        .uri(&format!("/history/{}/clicks", search_id))
+        .set_json(json!({ "key": key }));
+    assert_eq!(call!(app, click).0, 204);
+    let click = test::TestRequest::post()
+        .uri("/history/999/clicks")
+        .set_json(json!({ "key": key }));
+    assert_eq!(call!(app, click).0, 404);
+
+    let (_, history) = call!(app, test::TestRequest::get().uri("/history"));
+    assert_eq!(history["enabled"], true);
+    let searches = history["searches"].as_array().unwrap();
+    assert_eq!(searches.len(), 2);
+    assert_eq!(searches[0]["query"], "sourdough");
+    assert_eq!(searches[1]["query"], "rust -bread");
+    assert_eq!(searches[1]["filters"], json!(["-bread"]));
+    assert_eq!(searches[1]["clicked"], json!([key]));
+
+    let delete = test::TestRequest::delete().uri(&format!("/history/{}", search_id));
+    assert_eq!(call!(app, delete).1, json!({ "deleted": 1 }));
+    let delete = test::TestRequest::delete().uri(&format!("/history/{}", search_id));
+    assert_eq!(call!(app, delete).0, 404);
+
+    let disable = test::TestRequest::put()
+        .uri("/history/enabled")
+        .set_json(json!({ "enabled": false }));
+    assert_eq!(call!(app, disable).1, json!({ "enabled": false }));
+    let request = test::TestRequest::get().uri("/search?query=kubernetes");
+    let (_, response) = call!(app, request);
+    assert!(response.get("search_id").is_none());
+
+    let (_, history) = call!(app, test::TestRequest::delete().uri("/history"));
+    assert_eq!(history, json!({ "deleted": 1 }));
+    let (_, history) = call!(app, test::TestRequest::get().uri("/history"));
+    assert_eq!(history, json!({ "enabled": false, "searches": [] }));
+
+    // Turning history off outlasts a restart.
+    drop(app);
+    let app = app!(config);
+    let (_, history) = call!(app, test::TestRequest::get().uri("/history"));
+    assert_eq!(history["enabled"], false);
+}
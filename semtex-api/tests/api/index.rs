@@ -0,0 +1,326 @@
+//! Indexes kept across restarts, and rebuilt when the model or backend changes.
+
+use actix_web::test;
+use entity::content;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use semtex_api::config::{read_config, Config, IndexConfig};
+use semtex_api::store::Backend;
+use semtex_api::{connect, flush_index, start, try_start};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+use crate::{config, corpus, item, metadata, MODEL};
+
+#[actix_web::test]
+async fn index_survives_restart() {
+    let dir = TempDir::new().unwrap();
+    {
+        let app = app!(config(&dir, MODEL));
+        ingest!(app, corpus());
+    }
+
+    let app = app!(config(&dir, MODEL));
+    assert_eq!(search!(app, "sourdough starter")[0], "bread");
+    assert_eq!(metadata(&dir, "model"), MODEL);
+}
+
+#[actix_web::test]
+async fn changing_model_reindexes_content() {
+    let dir = TempDir::new().unwrap();
+    {
+        let app = app!(config(&dir, MODEL));
+        ingest!(app, corpus());
+    }
+
+    let state = start(&config(&dir, "semtex/fake-64")).await;
+    let app = service!(state.clone());
+    flush_index(&state).await;
+    assert_eq!(metadata(&dir, "model"), "semtex/fake-64");
+
+    assert_eq!(search!(app, "sourdough starter")[0], "bread");
+    assert_eq!(search!(app, "pods on nodes").len(), 3);
+
+    ingest!(app, vec![item("hubble", "the universe is expanding")]);
+    assert_eq!(search!(app, "expanding universe")[0], "hubble");
+}
+
+#[actix_web::test]
+async fn unknown_models_are_reported() {
+    let dir = TempDir::new().unwrap();
+    let error = try_start(&config(&dir, "semtex/nope")).await.err().unwrap();
+    assert!(error.contains("`semtex/nope`"), "{}", error);
+
+    {
+        let app = app!(config(&dir, MODEL));
+        ingest!(app, corpus());
+    }
+    // A model to migrate to is loaded before the migration starts.
+    let error = try_start(&config(&dir, "semtex/fake-64:q9"))
+        .await
+        .err()
+        .unwrap();
+    assert!(error.contains("`q9`"), "{}", error);
+    assert_eq!(metadata(&dir, "model"), MODEL);
+}
+
+#[actix_web::test]
+async fn broken_indexes_are_reported() {
+    let dir = TempDir::new().unwrap();
+    {
+        let app = app!(config(&dir, MODEL));
+        ingest!(app, corpus());
+    }
+
+    let path = dir.path().join("index.json");
+    let saved = std::fs::read_to_string(&path).unwrap();
+    let mut mismatched: Value = serde_json::from_str(&saved).unwrap();
+    mismatched["dimensions"] = json!(64);
+    std::fs::write(&path, mismatched.to_string()).unwrap();
+    let error = try_start(&config(&dir, MODEL)).await.err().unwrap();
+    assert!(error.contains("64"), "{}", error);
+    assert!(error.contains("doctor --repair"), "{}", error);
+
+    std::fs::write(&path, saved).unwrap();
+    std::fs::write(dir.path().join("index.usearch"), "not an index").unwrap();
+    let error = try_start(&config(&dir, MODEL)).await.err().unwrap();
+    assert!(error.contains("doctor --repair"), "{}", error);
+}
+
+#[actix_web::test]
+async fn truncated_exact_store_is_reported_at_startup() {
+    let dir = TempDir::new().unwrap();
+    let exact = Config {
+        index: IndexConfig {
+            backend: Backend::Exact,
+            ..IndexConfig::default()
+        },
+        ..config(&dir, MODEL)
+    };
+    {
+        let app = app!(exact);
+        ingest!(app, corpus());
+    }
+
+    let path = dir.path().join("index.exact");
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+    let error = try_start(&exact).await.err().unwrap();
+    assert!(error.contains("index.exact"), "{}", error);
+}
+
+#[actix_web::test]
+async fn invalid_config_is_reported_with_its_path() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, "model = 3\n").unwrap();
+    let error = read_config(&path).err().unwrap();
+    assert!(error.starts_with(&path.display().to_string()), "{}", error);
+}
+
+#[actix_web::test]
+async fn changing_backend_rebuilds_index() {
+    let dir = TempDir::new().unwrap();
+    {
+        let app = app!(config(&dir, MODEL));
+        ingest!(app, corpus());
+    }
+
+    let exact = Config {
+        index: IndexConfig {
+            backend: Backend::Exact,
+            ..IndexConfig::default()
+        },
+        ..config(&dir, MODEL)
+    };
+    let state = start(&exact).await;
+    let app = service!(state.clone());
+    flush_index(&state).await;
+    assert_eq!(metadata(&dir, "backend"), "exact");
+
+    assert_eq!(search!(app, "sourdough starter")[0], "bread");
+    assert!(dir.path().join("index.exact").exists());
+    assert!(!dir.path().join("index.usearch").exists());
+
+    let request = test::TestRequest::get().uri("/stats").to_request();
+    let stats: Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(stats["backend"], "exact");
+    assert_eq!(stats["size"], 3);
+}
+
+#[actix_web::test]
+async fn compaction_swaps_in_rebuilt_index() {
+    let dir = TempDir::new().unwrap();
+    let state = start(&config(&dir, MODEL)).await;
+    let app = service!(state.clone());
+    ingest!(app, corpus());
+
+    let request = test::TestRequest::post().uri("/compact").to_request();
+    let response: Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(response["started"], true);
+
+    // Searches and ingests keep working while the shadow index is built.
+    assert_eq!(search!(app, "sourdough starter")[0], "bread");
+    ingest!(app, vec![item("hubble", "the universe is expanding")]);
+
+    flush_index(&state).await;
+    let stats = stats!(app);
+    assert_eq!(stats["rebuilding"], false);
+    assert_eq!(stats["size"], 4);
+    assert_eq!(search!(app, "expanding universe")[0], "hubble");
+    assert!(!dir.path().join("index.migration.usearch").exists());
+}
+
+fn key_of(response: &Value, title: &str) -> String {
+    let results = response["results"].as_array().unwrap();
+    let result = results.iter().find(|r| r["title"] == title).unwrap();
+    result["key"].as_str().unwrap().to_string()
+}
+
+#[actix_web::test]
+async fn deleted_pages_leave_search_and_index() {
+    let dir = TempDir::new().unwrap();
+    let config = config(&dir, MODEL);
+    let state = start(&config).await;
+    let app = service!(state.clone());
+    ingest!(app, corpus());
+
+    let (_, all) = call!(app, test::TestRequest::get().uri("/search?query=rust"));
+    let bread = key_of(&all, "bread");
+    let rust = key_of(&all, "rust");
+
+    let delete = || test::TestRequest::delete().uri(&format!("/content/{}", bread));
+    assert_eq!(call!(app, delete()).0, 204);
+    assert_eq!(call!(app, delete()).0, 404);
+    let get = test::TestRequest::get().uri(&format!("/content/{}", bread));
+    assert_eq!(call!(app, get).0, 404);
+    assert!(!search!(app, "sourdough starter").contains(&"bread".to_string()));
+    let similar = test::TestRequest::get().uri(&format!("/content/{}/similar", rust));
+    let (_, similar) = call!(app, similar);
+    assert_eq!(similar["results"].as_array().unwrap().len(), 1);
+    assert_eq!(similar["results"][0]["title"], "kubernetes");
+    assert_eq!(stats!(app)["size"], 2);
+
+    // A row deleted behind the API's back loses its vector on compaction.
+    content::Entity::delete_many()
+        .filter(content::Column::Title.eq("kubernetes"))
+        .exec(&connect(&config).await)
+        .await
+        .unwrap();
+    assert_eq!(stats!(app)["size"], 2);
+    call!(app, test::TestRequest::post().uri("/compact"));
+    flush_index(&state).await;
+    assert_eq!(stats!(app)["size"], 1);
+    assert_eq!(search!(app, "pods on nodes"), ["rust"]);
+}
+
+async fn stored_embeddings(config: &Config) -> Vec<(Option<String>, usize)> {
+    content::Entity::find()
+        .all(&connect(config).await)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| (r.embedding_model, r.embedding.map_or(0, |e| e.len())))
+        .collect()
+}
+
+#[actix_web::test]
+async fn embeddings_are_stored_with_content() {
+    let dir = TempDir::new().unwrap();
+    {
+        let app = app!(config(&dir, MODEL));
+        ingest!(app, corpus());
+    }
+    assert_eq!(
+        stored_embeddings(&config(&dir, MODEL)).await,
+        vec![(Some(MODEL.to_string()), 256 * 4); 3]
+    );
+
+    let state = start(&config(&dir, "semtex/fake-64")).await;
+    let app = service!(state.clone());
+    flush_index(&state).await;
+    assert_eq!(
+        stored_embeddings(&config(&dir, MODEL)).await,
+        vec![(Some("semtex/fake-64".to_string()), 64 * 4); 3]
+    );
+    assert_eq!(search!(app, "sourdough starter")[0], "bread");
+}
+
+#[actix_web::test]
+async fn memory_mapped_index_merges_its_delta() {
+    let dir = TempDir::new().unwrap();
+    let mapped = Config {
+        index: IndexConfig {
+            memory_mapped: true,
+            merge_threshold: 3,
+            ..IndexConfig::default()
+        },
+        ..config(&dir, MODEL)
+    };
+    let state = start(&mapped).await;
+    let app = service!(state.clone());
+
+    ingest!(app, corpus()[..2].to_vec());
+    assert_eq!(stats!(app)["delta"], 2);
+    assert_eq!(search!(app, "sourdough starter")[0], "bread");
+
+    ingest!(app, corpus()[2..].to_vec());
+    flush_index(&state).await;
+
+    assert_eq!(stats!(app)["delta"], 0);
+    assert_eq!(stats!(app)["size"], 3);
+    assert_eq!(search!(app, "pods on nodes")[0], "kubernetes");
+    assert!(!dir.path().join("index.usearch.delta").exists());
+}
+
+#[actix_web::test]
+async fn unmapped_index_merges_its_delta() {
+    let dir = TempDir::new().unwrap();
+    {
+        let mapped = Config {
+            index: IndexConfig {
+                memory_mapped: true,
+                ..IndexConfig::default()
+            },
+            ..config(&dir, MODEL)
+        };
+        let app = app!(mapped);
+        ingest!(app, corpus());
+        assert_eq!(stats!(app)["delta"], 3);
+    }
+
+    let state = start(&config(&dir, MODEL)).await;
+    let app = service!(state.clone());
+    flush_index(&state).await;
+    assert_eq!(stats!(app)["delta"], 0);
+    assert!(!dir.path().join("index.usearch.delta").exists());
+
+    ingest!(app, vec![item("hubble", "the universe is expanding")]);
+    assert_eq!(stats!(app)["rebuilding"], false);
+    assert_eq!(stats!(app)["size"], 4);
+    assert_eq!(search!(app, "expanding universe")[0], "hubble");
+}
+
+#[actix_web::test]
+async fn index_keyed_by_row_id_is_rebuilt() {
+    let dir = TempDir::new().unwrap();
+    {
+        let app = app!(config(&dir, MODEL));
+        ingest!(app, corpus());
+    }
+
+    let path = dir.path().join("index.json");
+    let mut legacy: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    legacy["keys"] = json!("row_id");
+    std::fs::write(&path, legacy.to_string()).unwrap();
+
+    let state = start(&config(&dir, MODEL)).await;
+    let app = service!(state.clone());
+    flush_index(&state).await;
+    assert_eq!(metadata(&dir, "keys"), "stable");
+    assert_eq!(search!(app, "sourdough starter")[0], "bread");
+
+    let request = test::TestRequest::get().uri("/search?query=bread").to_request();
+    let response: Value = test::call_and_read_body_json(&app, request).await;
+    let key = response["results"][0]["key"].as_str().unwrap();
+    assert!(key.parse::<u64>().is_ok());
+}
@@ -0,0 +1,110 @@
+//! End-to-end tests of the HTTP API against a temporary data directory, using
+//! the deterministic `semtex/fake-*` models so nothing is downloaded. Each
+//! module covers one feature; the fixtures they share are defined here.
+
+use semtex_api::config::Config;
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+const MODEL: &str = "semtex/fake-256";
+
+fn config(dir: &TempDir, model: &str) -> Config {
+    Config {
+        model: model.to_string(),
+        embedding_workers: 1,
+        data_dir: dir.path().to_path_buf(),
+        ..Config::default()
+    }
+}
+
+fn item(title: &str, content: &str) -> Value {
+    json!({
+        "title": title,
+        "content": content,
+        "source": { "name": "test", "url": format!("https://example.com/{}", title) },
+    })
+}
+
+fn corpus() -> Vec<Value> {
+    vec![
+        item("rust", "async rust futures are polled by an executor"),
+        item("bread", "sourdough bread needs a starter and a long proof"),
+        item("kubernetes", "kubernetes schedules pods onto nodes"),
+    ]
+}
+
+/// Serves the app over `state`, for tests that also need the state itself.
+macro_rules! service {
+    ($state:expr) => {{
+        use actix_web::{test, App};
+        test::init_service(App::new().app_data($state).configure(semtex_api::configure)).await
+    }};
+}
+
+macro_rules! app {
+    ($config:expr) => {
+        service!(semtex_api::start(&$config).await)
+    };
+}
+
+macro_rules! ingest {
+    ($app:expr, $items:expr) => {{
+        use actix_web::test;
+        let request = test::TestRequest::post()
+            .uri("/ingest")
+            .set_json(serde_json::json!({ "items": $items }))
+            .to_request();
+        String::from_utf8(test::call_and_read_body(&$app, request).await.to_vec()).unwrap()
+    }};
+}
+
+macro_rules! search {
+    ($app:expr, $query:expr) => {{
+        use actix_web::test;
+        let request = test::TestRequest::get()
+            .uri(&format!("/search?query={}", $query.replace(' ', "%20")))
+            .to_request();
+        let response: serde_json::Value = test::call_and_read_body_json(&$app, request).await;
+        response["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["title"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    }};
+}
+
+macro_rules! stats {
+    ($app:expr) => {{
+        use actix_web::test;
+        let request = test::TestRequest::get().uri("/stats").to_request();
+        test::call_and_read_body_json::<_, _, serde_json::Value>(&$app, request).await
+    }};
+}
+
+macro_rules! call {
+    ($app:expr, $request:expr) => {{
+        use actix_web::test;
+        use serde_json::Value;
+        let response = test::call_service(&$app, $request.to_request()).await;
+        let status = response.status().as_u16();
+        let body = test::read_body(response).await;
+        let body = serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null);
+        (status, body)
+    }};
+}
+
+fn metadata(dir: &TempDir, field: &str) -> String {
+    let metadata = std::fs::read_to_string(dir.path().join("index.json")).unwrap();
+    serde_json::from_str::<Value>(&metadata).unwrap()[field]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+mod history;
+mod index;
+mod operators;
+mod ranking;
+mod saved_searches;
+mod search;
@@ -0,0 +1,136 @@
+//! Query operators and `explain`.
+
+use actix_web::test;
+use entity::content;
+use sea_orm::{ActiveValue, ColumnTrait, EntityTrait, QueryFilter};
+use semtex_api::connect;
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+use crate::{config, corpus, MODEL};
+
+fn page(title: &str, content: &str, source: &str, host: &str) -> Value {
+    json!({
+        "title": title,
+        "content": content,
+        "source": { "name": source, "url": format!("https://{}/{}", host, title) },
+    })
+}
+
+#[actix_web::test]
+async fn query_operators_filter_results() {
+    let dir = TempDir::new().unwrap();
+    let config = config(&dir, MODEL);
+    let app = app!(config);
+    ingest!(
+        app,
+        [
+            page("rust", "async rust futures", "test", "github.com"),
+            page("tokio", "tokio rust futures", "test", "blog.github.com"),
+            page("docs", "rust standard library docs", "notes", "docs.rs"),
+            page("bread", "a long proof", "notes", "example.com"),
+            page("mirror", "a copy of a forge", "test", "notgithub.com"),
+        ]
+    );
+
+    content::Entity::update_many()
+        .set(content::ActiveModel {
+            created_at: ActiveValue::Set("2020-01-01T00:00:00+00:00".to_string()),
+            ..Default::default()
+        })
+        .filter(content::Column::Title.eq("docs"))
+        .exec(&connect(&config).await)
+        .await
+        .unwrap();
+
+    let mut on_github = search!(app, "rust site:github.com");
+    on_github.sort();
+    assert_eq!(on_github, ["rust", "tokio"]);
+    assert_eq!(search!(app, "rust source:notes")[0], "docs");
+    assert_eq!(search!(app, "rust source:notes").len(), 2);
+    assert!(!search!(app, "rust after:2021").contains(&"docs".to_string()));
+    assert_eq!(search!(app, "rust before:2021-06"), ["docs"]);
+    assert_eq!(search!(app, "futures -tokio")[0], "rust");
+    assert!(!search!(app, "futures -tokio").contains(&"tokio".to_string()));
+    assert_eq!(search!(app, "bread %22long proof%22"), ["bread"]);
+
+    // Operators alone list what matches them, newest first.
+    assert_eq!(search!(app, "source:notes"), ["bread", "docs"]);
+    assert_eq!(search!(app, "site:docs.rs"), ["docs"]);
+    let mut on_github = search!(app, "site:github.com");
+    on_github.sort();
+    assert_eq!(on_github, ["rust", "tokio"]);
+    assert!(search!(app, "site:nowhere.org").is_empty());
+    // Unknown operators are searched as text.
+    assert_eq!(search!(app, "rust lang:en").len(), 5);
+}
+
+#[actix_web::test]
+async fn excluded_terms_match_whole_words() {
+    let dir = TempDir::new().unwrap();
+    let app = app!(config(&dir, MODEL));
+    ingest!(
+        app,
+        [
+            page("search", "how google ranks pages", "test", "example.com"),
+            page("lang", "go is a language, rust too", "test", "example.com"),
+            page("faith", "trust the compiler", "test", "example.com"),
+        ]
+    );
+
+    let mut kept = search!(app, "pages -go");
+    kept.sort();
+    assert_eq!(kept, ["faith", "search"]);
+    let mut kept = search!(app, "compiler -rust");
+    kept.sort();
+    assert_eq!(kept, ["faith", "search"]);
+    // Operators alone list what matches them, newest first.
+    assert_eq!(search!(app, "-rust"), ["faith", "search"]);
+    assert_eq!(search!(app, "-%22a language%22"), ["faith", "search"]);
+}
+
+#[actix_web::test]
+async fn explain_reports_scores_filters_and_timings() {
+    let dir = TempDir::new().unwrap();
+    let app = app!(config(&dir, MODEL));
+    ingest!(app, corpus());
+
+    let request = test::TestRequest::get()
+        .uri("/search?query=async%20rust%20site:example.com%20-bread")
+        .to_request();
+    let response: Value = test::call_and_read_body_json(&app, request).await;
+    assert!(response.get("explain").is_none());
+    assert!(response["results"][0].get("explain").is_none());
+
+    let request = test::TestRequest::get()
+        .uri("/search?query=async%20rust%20site:example.com%20-bread&explain=true")
+        .to_request();
+    let response: Value = test::call_and_read_body_json(&app, request).await;
+    let explain = &response["explain"];
+    assert_eq!(explain["text"], "async rust");
+    assert!(explain["embedding_norm"].as_f64().unwrap() > 0.0);
+    assert_eq!(
+        explain["filters"],
+        json!([
+            { "operator": "site:example.com", "matching": 3 },
+            { "operator": "-bread", "matching": 2 },
+        ])
+    );
+    assert_eq!(explain["allowed"], 2);
+    assert_eq!(explain["candidates"], 2);
+    for stage in ["filter", "embed", "search", "fetch", "snippets", "total"] {
+        assert!(explain["timings"][stage].as_f64().unwrap() >= 0.0);
+    }
+    assert!(explain["timings"].get("rerank").is_none());
+
+    let results = response["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["title"], "rust");
+    let hit = &results[0]["explain"];
+    assert_eq!(hit["nearest_rank"], 0);
+    assert_eq!(hit["lexical_score"], 1.0);
+    let similarity = 1.0 - results[0]["distance"].as_f64().unwrap();
+    assert!((hit["fused_score"].as_f64().unwrap() - similarity).abs() < 1e-6);
+    assert_eq!(results[1]["explain"]["nearest_rank"], 1);
+    assert_eq!(results[1]["explain"]["lexical_score"], 0.0);
+}
@@ -0,0 +1,198 @@
+//! Reranking, diversification and recency ranking of results.
+
+use actix_web::test;
+use entity::content;
+use sea_orm::{ActiveValue, ColumnTrait, EntityTrait, QueryFilter};
+use semtex_api::config::{Config, DiversifyConfig, RankingConfig, RerankConfig};
+use semtex_api::connect;
+use serde_json::Value;
+use tempfile::TempDir;
+
+use crate::{config, item, MODEL};
+
+fn reranking(dir: &TempDir, budget_ms: u64) -> Config {
+    Config {
+        rerank: Some(RerankConfig {
+            model: "semtex/fake-reranker".to_string(),
+            candidates: 10,
+            budget_ms,
+            by_default: false,
+        }),
+        ..config(dir, MODEL)
+    }
+}
+
+/// The embedding favours `first`, which repeats nothing else; the reranker
+/// favours `second`, which contains every query word.
+fn ambiguous() -> Vec<Value> {
+    vec![
+        item("first", "apple"),
+        item("second", "apple cherry one two three four five six seven"),
+    ]
+}
+
+#[actix_web::test]
+async fn rerank_reorders_results_on_request() {
+    let dir = TempDir::new().unwrap();
+    let app = app!(reranking(&dir, 10_000));
+    ingest!(app, ambiguous());
+
+    assert_eq!(search!(app, "apple cherry"), vec!["first", "second"]);
+    assert_eq!(search!(app, "apple cherry&rerank=true"), vec!["second", "first"]);
+
+    let request = test::TestRequest::get()
+        .uri("/search?query=apple%20cherry&rerank=true")
+        .to_request();
+    let response: Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(response["results"][0]["rerank_score"], 1.0);
+}
+
+#[actix_web::test]
+async fn rerank_past_budget_keeps_nearest_first() {
+    let dir = TempDir::new().unwrap();
+    let app = app!(reranking(&dir, 0));
+    ingest!(app, ambiguous());
+
+    let request = test::TestRequest::get()
+        .uri("/search?query=apple%20cherry&rerank=true")
+        .to_request();
+    let response: Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(response["results"][0]["title"], "first");
+    assert!(response["results"][0].get("rerank_score").is_none());
+}
+
+#[actix_web::test]
+async fn diversify_demotes_mirrors() {
+    let dir = TempDir::new().unwrap();
+    let config = Config {
+        diversify: Some(DiversifyConfig {
+            lambda: 0.5,
+            by_default: false,
+            ..DiversifyConfig::default()
+        }),
+        ..config(&dir, MODEL)
+    };
+    let app = app!(config);
+    ingest!(
+        app,
+        [
+            item("rust", "async rust futures are polled by an executor"),
+            item("mirror", "async rust futures are polled by an executor"),
+            item("tokio", "tokio is an executor for rust futures and io"),
+            item("bread", "sourdough bread needs a starter"),
+        ]
+    );
+
+    let query = "async rust futures executor";
+    let nearest = search!(app, query);
+    assert_eq!(nearest[2], "tokio");
+
+    let diverse = search!(app, format!("{}&diversify=true", query));
+    assert_eq!(diverse[1], "tokio");
+    assert_eq!(diverse.len(), 4);
+
+    // All test pages are on example.com.
+    let capped = search!(app, format!("{}&diversify=true&max_per_domain=2", query));
+    assert_eq!(capped, diverse[..2]);
+}
+
+#[actix_web::test]
+async fn ranking_keeps_its_order_across_pages() {
+    let dir = TempDir::new().unwrap();
+    let config = Config {
+        ranking: Some(RankingConfig {
+            recency_weight: 1.0,
+            candidates: 2,
+            ..RankingConfig::default()
+        }),
+        ..config(&dir, MODEL)
+    };
+    let app = app!(config);
+    ingest!(
+        app,
+        [
+            item("one", "sourdough starter bread recipe"),
+            item("two", "sourdough starter bread notes"),
+            item("three", "sourdough starter cake notes"),
+            item("four", "sourdough pasta cake notes"),
+        ]
+    );
+
+    // Stored years ago.
+    content::Entity::update_many()
+        .set(content::ActiveModel {
+            created_at: ActiveValue::Set("2020-01-01T00:00:00+00:00".to_string()),
+            ..Default::default()
+        })
+        .filter(content::Column::Title.eq("one"))
+        .exec(&connect(&config).await)
+        .await
+        .unwrap();
+
+    let query = "sourdough%20starter%20bread%20recipe";
+    assert_eq!(
+        search!(app, format!("{}&rank=false", query)),
+        ["one", "two", "three", "four"]
+    );
+    // Only the nearest two are ranked; the rest follow nearest first.
+    let ranked = search!(app, query);
+    assert_eq!(ranked, ["two", "one", "three", "four"]);
+    for offset in 0..ranked.len() {
+        let page = search!(app, format!("{}&offset={}&limit=1", query, offset));
+        assert_eq!(page, ranked[offset..offset + 1]);
+    }
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/search?query={}&offset=2", query))
+        .to_request();
+    let response: Value = test::call_and_read_body_json(&app, request).await;
+    assert!(response["results"][0].get("score").is_none());
+}
+
+#[actix_web::test]
+async fn ranking_favours_recent_and_visited_pages() {
+    let dir = TempDir::new().unwrap();
+    let config = Config {
+        ranking: Some(RankingConfig {
+            recency_weight: 1.0,
+            half_life_days: 30.0,
+            ..RankingConfig::default()
+        }),
+        ..config(&dir, MODEL)
+    };
+    let app = app!(config);
+    ingest!(
+        app,
+        [
+            item("old", "sourdough starter bread recipe"),
+            item("new", "sourdough starter bread guide notes"),
+            item("new", "sourdough starter bread guide notes"),
+        ]
+    );
+
+    // Stored years ago.
+    content::Entity::update_many()
+        .set(content::ActiveModel {
+            created_at: ActiveValue::Set("2020-01-01T00:00:00+00:00".to_string()),
+            ..Default::default()
+        })
+        .filter(content::Column::Title.eq("old"))
+        .exec(&connect(&config).await)
+        .await
+        .unwrap();
+
+    let query = "sourdough starter bread recipe";
+    assert_eq!(search!(app, format!("{}&rank=false", query))[0], "old");
+    assert_eq!(search!(app, query)[0], "new");
+
+    let request = test::TestRequest::get()
+        .uri("/search?query=sourdough%20starter%20bread%20recipe")
+        .to_request();
+    let response: Value = test::call_and_read_body_json(&app, request).await;
+    let score = &response["results"][0]["score"];
+    assert_eq!(score["visit_count"], 2);
+    assert!(score["recency"].as_f64().unwrap() > 0.99);
+    let old = &response["results"].as_array().unwrap().last().unwrap()["score"];
+    assert_eq!(old["visit_count"], 1);
+    assert!(old["age_days"].as_f64().unwrap() > 365.0);
+}
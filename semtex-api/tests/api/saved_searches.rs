@@ -0,0 +1,146 @@
+//! Saved searches and the alerts they raise.
+
+use actix_web::test;
+use entity::{alert, content};
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+use semtex_api::connect;
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+use crate::{config, corpus, item, MODEL};
+
+#[actix_web::test]
+async fn saved_searches_are_named_pinned_and_run() {
+    let dir = TempDir::new().unwrap();
+    let app = app!(config(&dir, MODEL));
+    ingest!(app, corpus());
+
+    let create = |name: &str, query: &str, pinned: bool| {
+        test::TestRequest::post()
+            .uri("/saved-searches")
+            .set_json(json!({ "name": name, "query": query, "pinned": pinned }))
+    };
+    let (status, bread) = call!(app, create("baking", "sourdough starter", false));
+    assert_eq!(status, 201);
+    assert_eq!(bread["pinned"], false);
+    call!(app, create("clusters", "kubernetes pods", true));
+
+    let (_, saved) = call!(app, test::TestRequest::get().uri("/saved-searches"));
+    let names = |saved: &Value| {
+        saved["saved_searches"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["name"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(&saved), ["clusters", "baking"]);
+
+    let update = test::TestRequest::put()
+        .uri(&format!("/saved-searches/{}", bread["id"]))
+        .set_json(json!({ "name": "bread", "pinned": true }));
+    let (_, updated) = call!(app, update);
+    assert_eq!(updated["name"], "bread");
+    assert_eq!(updated["query"], "sourdough starter");
+    let (_, saved) = call!(app, test::TestRequest::get().uri("/saved-searches"));
+    assert_eq!(names(&saved), ["bread", "clusters"]);
+
+    let run = test::TestRequest::get().uri(&format!("/saved-searches/{}/results", bread["id"]));
+    let (_, response) = call!(app, run);
+    assert_eq!(response["results"][0]["title"], "bread");
+    assert!(response["search_id"].is_i64());
+
+    let delete = test::TestRequest::delete().uri(&format!("/saved-searches/{}", bread["id"]));
+    assert_eq!(call!(app, delete).0, 204);
+    let run = test::TestRequest::get().uri(&format!("/saved-searches/{}/results", bread["id"]));
+    assert_eq!(call!(app, run).0, 404);
+    let (_, saved) = call!(app, test::TestRequest::get().uri("/saved-searches"));
+    assert_eq!(names(&saved), ["clusters"]);
+}
+
+#[actix_web::test]
+async fn newly_indexed_pages_matching_saved_searches_raise_alerts() {
+    let dir = TempDir::new().unwrap();
+    let config = config(&dir, MODEL);
+    let app = app!(config);
+
+    let save = |query: &str, alert: bool| {
+        test::TestRequest::post()
+            .uri("/saved-searches")
+            .set_json(json!({
+                "name": query,
+                "query": query,
+                "alert": alert,
+                "alert_threshold": 0.3,
+            }))
+    };
+    let (_, bread) = call!(app, save("sourdough starter", true));
+    assert_eq!(bread["alert"], true);
+    call!(app, save("kubernetes pods site:elsewhere.org", true));
+    call!(app, save("kubernetes pods", false));
+    ingest!(app, corpus());
+
+    let (_, response) = call!(app, test::TestRequest::get().uri("/alerts"));
+    let alerts = response["alerts"].as_array().unwrap();
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0]["saved_search_id"], bread["id"]);
+    assert_eq!(alerts[0]["name"], "sourdough starter");
+    assert_eq!(alerts[0]["title"], "bread");
+    assert_eq!(alerts[0]["url"], "https://example.com/bread");
+    assert_eq!(alerts[0]["seen"], false);
+    assert!(alerts[0]["similarity"].as_f64().unwrap() >= 0.3);
+    assert!(alerts[0]["key"].is_string());
+    // Saved queries are not embedded through the search query cache.
+    assert_eq!(stats!(app)["query_cache"]["entries"], 0);
+
+    // Pages indexed before a search was saved do not raise alerts.
+    let (_, tokio) = call!(app, save("tokio runtime", true));
+    ingest!(
+        app,
+        [item("tokio", "the tokio runtime drives rust futures")]
+    );
+    let (_, response) = call!(app, test::TestRequest::get().uri("/alerts"));
+    assert_eq!(response["alerts"][0]["title"], "tokio");
+    assert_eq!(response["alerts"].as_array().unwrap().len(), 2);
+
+    let seen = test::TestRequest::post()
+        .uri("/alerts/seen")
+        .set_json(json!({ "ids": [response["alerts"][1]["id"]] }));
+    assert_eq!(call!(app, seen).1, json!({ "updated": 1 }));
+    let (_, unseen) = call!(app, test::TestRequest::get().uri("/alerts?unseen=true"));
+    assert_eq!(unseen["alerts"].as_array().unwrap().len(), 1);
+    assert_eq!(unseen["alerts"][0]["title"], "tokio");
+    assert_eq!(unseen["unseen"], 1);
+
+    // Deleting a page deletes its alerts.
+    let db = connect(&config).await;
+    content::Entity::delete_many()
+        .filter(content::Column::Title.eq("bread"))
+        .exec(&db)
+        .await
+        .unwrap();
+    assert_eq!(alert::Entity::find().count(&db).await.unwrap(), 1);
+    let (_, response) = call!(app, test::TestRequest::get().uri("/alerts"));
+    assert_eq!(response["alerts"].as_array().unwrap().len(), 1);
+
+    // A threshold is kept unless changed, and cleared with null.
+    let update = |changes: Value| {
+        test::TestRequest::put()
+            .uri(&format!("/saved-searches/{}", tokio["id"]))
+            .set_json(changes)
+    };
+    let (_, updated) = call!(app, update(json!({ "alert": true })));
+    assert!((updated["alert_threshold"].as_f64().unwrap() - 0.3).abs() < 1e-6);
+    let (_, updated) = call!(app, update(json!({ "alert_threshold": null })));
+    assert_eq!(updated.get("alert_threshold"), None);
+
+    // Deleting a saved search deletes its alerts.
+    let delete = test::TestRequest::delete().uri(&format!("/saved-searches/{}", tokio["id"]));
+    call!(app, delete);
+    let (_, unseen) = call!(app, test::TestRequest::get().uri("/alerts?unseen=true"));
+    assert_eq!(unseen["alerts"], json!([]));
+    let all_seen = test::TestRequest::post()
+        .uri("/alerts/seen")
+        .set_json(json!({}));
+    assert_eq!(call!(app, all_seen).1, json!({ "updated": 0 }));
+}
@@ -0,0 +1,193 @@
+//! Searching, paging, similar pages and snippets.
+
+use actix_web::test;
+use semtex_api::config::{Config, SnippetConfig};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+use crate::{config, corpus, item, MODEL};
+
+#[actix_web::test]
+async fn search_ranks_matching_document_first() {
+    let dir = TempDir::new().unwrap();
+    let app = app!(config(&dir, MODEL));
+
+    assert_eq!(ingest!(app, corpus()), "ingested 3");
+
+    assert_eq!(search!(app, "sourdough starter")[0], "bread");
+    assert_eq!(search!(app, "pods on nodes")[0], "kubernetes");
+    assert_eq!(search!(app, "rust futures").len(), 3);
+}
+
+#[actix_web::test]
+async fn search_on_empty_index_returns_nothing() {
+    let dir = TempDir::new().unwrap();
+    let app = app!(config(&dir, MODEL));
+
+    assert!(search!(app, "anything").is_empty());
+}
+
+#[actix_web::test]
+async fn search_is_paginated() {
+    let dir = TempDir::new().unwrap();
+    let app = app!(config(&dir, MODEL));
+    ingest!(app, corpus());
+
+    // Matches each page on a different number of words, so none are tied.
+    let query = "async rust futures pods nodes bread";
+    let all = search!(app, query);
+    assert_eq!(all, ["rust", "kubernetes", "bread"]);
+    assert_eq!(search!(app, format!("{}&limit=2", query)), all[..2]);
+    assert_eq!(search!(app, format!("{}&offset=1&limit=1", query)), all[1..2]);
+    assert!(search!(app, format!("{}&offset=3", query)).is_empty());
+}
+
+macro_rules! similar {
+    ($app:expr, $key:expr, $params:expr) => {{
+        let request = test::TestRequest::get()
+            .uri(&format!("/content/{}/similar?{}", $key, $params))
+            .to_request();
+        test::call_service(&$app, request).await
+    }};
+}
+
+fn titles(response: &Value) -> Vec<String> {
+    response["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["title"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[actix_web::test]
+async fn similar_content_leaves_out_document_and_duplicates() {
+    let dir = TempDir::new().unwrap();
+    let app = app!(config(&dir, MODEL));
+    let mut items = vec![
+        item("bread", "sourdough bread needs a starter and a long proof"),
+        item("bread", "sourdough bread needs a starter and a long proof"),
+        item("baking", "sourdough loaves need a long proof"),
+        item("starter", "feed the starter before baking bread"),
+        item("oven", "an oven needs heat"),
+    ];
+    items.push(json!({
+        "title": "bread mirror",
+        "content": "a mirror of the sourdough article",
+        "source": { "name": "test", "url": "https://example.com/bread" },
+    }));
+    ingest!(app, items);
+
+    let request = test::TestRequest::get()
+        .uri("/search?query=sourdough%20starter")
+        .to_request();
+    let response: Value = test::call_and_read_body_json(&app, request).await;
+    let key = response["results"][0]["key"].as_str().unwrap().to_string();
+
+    let response: Value = test::read_body_json(similar!(app, key, "")).await;
+    let all = titles(&response);
+    assert_eq!(all, ["baking", "starter", "oven"]);
+
+    let response: Value = test::read_body_json(similar!(app, key, "offset=1&limit=1")).await;
+    assert_eq!(titles(&response), all[1..2]);
+
+    assert_eq!(similar!(app, "12345", "").status(), 404);
+}
+
+#[actix_web::test]
+async fn results_carry_snippets_and_full_text_on_request() {
+    let dir = TempDir::new().unwrap();
+    let app = app!(config(&dir, MODEL));
+    ingest!(
+        app,
+        [item(
+            "bread",
+            "Bread is old. A sourdough starter is flour and water. Bake it hot."
+        )]
+    );
+
+    let request = test::TestRequest::get()
+        .uri("/search?query=sourdough%20starter")
+        .to_request();
+    let response: Value = test::call_and_read_body_json(&app, request).await;
+    let result = &response["results"][0];
+    assert!(result.get("text").is_none());
+    // Two passages by default, in page order.
+    assert_eq!(result["snippets"][0]["text"], "Bread is old.");
+    assert_eq!(
+        result["snippets"][1],
+        json!({
+            "text": "A sourdough starter is flour and water.",
+            "offset": 14,
+            "highlights": [{ "start": 2, "end": 11 }, { "start": 12, "end": 19 }],
+        })
+    );
+
+    let key = result["key"].as_str().unwrap();
+    let request = test::TestRequest::get()
+        .uri(&format!("/content/{}", key))
+        .to_request();
+    let content: Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(
+        content["text"],
+        "Bread is old. A sourdough starter is flour and water. Bake it hot."
+    );
+
+    let request = test::TestRequest::get().uri("/content/12345").to_request();
+    assert_eq!(test::call_service(&app, request).await.status(), 404);
+}
+
+fn semantic_snippets(dir: &TempDir) -> Config {
+    Config {
+        snippets: SnippetConfig {
+            semantic: true,
+            ..SnippetConfig::default()
+        },
+        ..config(dir, MODEL)
+    }
+}
+
+#[actix_web::test]
+async fn semantic_snippets_embed_a_few_sentences_per_result() {
+    let dir = TempDir::new().unwrap();
+    let long = (0..20)
+        .map(|i| format!("Sourdough starter note {}.", i))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let items = [item("bread", &long), item("rolls", &long)];
+    let explained = |app| async move {
+        let request = test::TestRequest::get()
+            .uri("/search?query=sourdough%20starter&explain=true")
+            .to_request();
+        let response: Value = test::call_and_read_body_json(&app, request).await;
+        response["explain"]["embedded_sentences"].clone()
+    };
+
+    let app = app!(config(&dir, MODEL));
+    ingest!(app, items);
+    assert_eq!(explained(app).await, 0);
+
+    let dir = TempDir::new().unwrap();
+    let app = app!(semantic_snippets(&dir));
+    ingest!(app, items);
+    assert_eq!(explained(app).await, 2 * 3);
+}
+
+#[actix_web::test]
+async fn repeated_queries_are_embedded_once() {
+    let dir = TempDir::new().unwrap();
+    let app = app!(semantic_snippets(&dir));
+    ingest!(app, corpus());
+
+    let before = stats!(app)["query_cache"].clone();
+    assert_eq!(before["capacity"], 1024);
+    assert_eq!(search!(app, "sourdough starter")[0], "bread");
+    assert_eq!(search!(app, "sourdough starter")[0], "bread");
+
+    let after = &stats!(app)["query_cache"];
+    assert_eq!(after["entries"], 1);
+    // The first search misses; snippets and the second search hit.
+    assert_eq!(after["misses"], 1);
+    assert_eq!(after["hits"], 3);
+    assert_eq!(after["hit_rate"], 0.75);
+}
@@ -0,0 +1,139 @@
+//! Measures search latency on an idle index and while an import is writing
+//! to it, for one and for several search workers. Uses the fake model, so the
+//! numbers reflect index contention rather than embedding cost:
+//!
+//!     cargo test --release -p semtex-api --test concurrency -- --ignored --nocapture
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use actix_web::rt::spawn;
+use actix_web::web::Data;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use semtex_api::config::Config;
+use semtex_api::{
+    ingest_items, search_content, start, AppState, IngestItem, SearchParams, Source,
+};
+use tempfile::TempDir;
+
+const WORDS: &[&str] = &[
+    "rust", "async", "future", "executor", "kubernetes", "pod", "node", "bread", "sourdough",
+    "starter", "universe", "expansion", "sqlite", "index", "vector", "search", "graph",
+    "marathon", "interval", "training", "container", "runtime", "memory", "safety", "flavour",
+];
+const PRELOAD: usize = 5_000;
+const BATCH: usize = 64;
+const CLIENTS: usize = 8;
+const QUERIES_PER_CLIENT: usize = 50;
+
+fn text(rng: &mut StdRng, words: usize) -> String {
+    (0..words)
+        .map(|_| *WORDS.choose(rng).unwrap())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn batch(rng: &mut StdRng) -> Vec<IngestItem> {
+    (0..BATCH)
+        .map(|_| IngestItem {
+            title: text(rng, 4),
+            content: text(rng, 60),
+            source: Source {
+                name: "bench".to_string(),
+                url: None,
+            },
+        })
+        .collect()
+}
+
+async fn query_latencies(state: &Data<AppState>) -> Vec<Duration> {
+    let clients = (0..CLIENTS)
+        .map(|client| {
+            let state = state.clone();
+            spawn(async move {
+                let mut rng = StdRng::seed_from_u64(client as u64);
+                let mut latencies = Vec::new();
+                for _ in 0..QUERIES_PER_CLIENT {
+                    let query = text(&mut rng, 3);
+                    let start = Instant::now();
+                    search_content(&state, &SearchParams::new(&query)).await;
+                    latencies.push(start.elapsed());
+                }
+                latencies
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut latencies = Vec::new();
+    for client in clients {
+        latencies.extend(client.await.unwrap());
+    }
+    latencies.sort();
+    latencies
+}
+
+fn percentile(latencies: &[Duration], p: f64) -> f64 {
+    let i = ((latencies.len() - 1) as f64 * p).round() as usize;
+    latencies[i].as_secs_f64() * 1000.0
+}
+
+async fn run(search_workers: usize) {
+    let dir = TempDir::new().unwrap();
+    let config = Config {
+        model: "semtex/fake-384".to_string(),
+        search_workers,
+        data_dir: dir.path().to_path_buf(),
+        ..Config::default()
+    };
+    let state = start(&config).await;
+
+    let mut rng = StdRng::seed_from_u64(42);
+    for _ in 0..PRELOAD / BATCH {
+        ingest_items(&state, &batch(&mut rng)).await;
+    }
+
+    let idle = query_latencies(&state).await;
+
+    let importing = Arc::new(AtomicBool::new(true));
+    let imported = Arc::new(AtomicUsize::new(0));
+    let import = {
+        let state = state.clone();
+        let importing = importing.clone();
+        let imported = imported.clone();
+        spawn(async move {
+            let mut rng = StdRng::seed_from_u64(7);
+            while importing.load(Ordering::Relaxed) {
+                ingest_items(&state, &batch(&mut rng)).await;
+                imported.fetch_add(BATCH, Ordering::Relaxed);
+            }
+        })
+    };
+
+    let start = Instant::now();
+    let busy = query_latencies(&state).await;
+    let elapsed = start.elapsed();
+    importing.store(false, Ordering::Relaxed);
+    import.await.unwrap();
+
+    println!(
+        "{} search workers  idle p50 {:>7.2} ms  p95 {:>7.2} ms  |  importing p50 {:>7.2} ms  p95 {:>7.2} ms  ({:.0} docs/s imported)",
+        search_workers,
+        percentile(&idle, 0.5),
+        percentile(&idle, 0.95),
+        percentile(&busy, 0.5),
+        percentile(&busy, 0.95),
+        imported.load(Ordering::Relaxed) as f64 / elapsed.as_secs_f64()
+    );
+
+    assert_eq!(busy.len(), CLIENTS * QUERIES_PER_CLIENT);
+}
+
+#[actix_web::test]
+#[ignore]
+async fn search_latency_during_import() {
+    run(1).await;
+    run(4).await;
+}
@@ -0,0 +1,45 @@
+use semtex_api::diversify::{domain, mmr, Candidate};
+
+fn candidate<'a>(relevance: f32, vector: &'a [f32], url: &str) -> Candidate<'a> {
+    Candidate {
+        relevance,
+        vector,
+        domain: domain(url),
+    }
+}
+
+#[test]
+fn passes_over_near_duplicates() {
+    let candidates = [
+        candidate(0.9, &[1.0, 0.0], "https://blog.example.com/post"),
+        candidate(0.89, &[1.0, 0.01], "https://mirror.example.org/post"),
+        candidate(0.7, &[0.0, 1.0], "https://other.example.net/"),
+    ];
+
+    assert_eq!(mmr(&candidates, 1.0, None), [0, 1, 2]);
+    assert_eq!(mmr(&candidates, 0.5, None), [0, 2, 1]);
+}
+
+#[test]
+fn caps_results_per_domain() {
+    let candidates = [
+        candidate(0.9, &[1.0, 0.0], "https://www.example.com/a"),
+        candidate(0.8, &[0.0, 1.0], "https://example.com/b"),
+        candidate(0.7, &[1.0, 1.0], "https://example.org/c"),
+        candidate(0.6, &[1.0, -1.0], ""),
+    ];
+
+    assert_eq!(mmr(&candidates, 1.0, Some(1)), [0, 2, 3]);
+    assert_eq!(mmr(&candidates, 1.0, Some(2)), [0, 1, 2, 3]);
+}
+
+#[test]
+fn parses_domains() {
+    assert_eq!(
+        domain("https://WWW.Example.com:8080/a?b#c").unwrap(),
+        "example.com"
+    );
+    assert_eq!(domain("http://user@docs.rs/serde").unwrap(), "docs.rs");
+    assert_eq!(domain("example.com/page").unwrap(), "example.com");
+    assert_eq!(domain(""), None);
+}
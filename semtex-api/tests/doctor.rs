@@ -0,0 +1,101 @@
+use chrono::Utc;
+use entity::content;
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use semtex_api::config::Config;
+use semtex_api::doctor::{diagnose, repair};
+use semtex_api::index::{load_metadata, store_path, INDEX_NAME};
+use semtex_api::store::open_store;
+use semtex_api::{connect, ingest_items, start, IngestItem, Source};
+use tempfile::TempDir;
+
+fn config(dir: &TempDir) -> Config {
+    Config {
+        model: "semtex/fake-64".to_string(),
+        embedding_workers: 1,
+        data_dir: dir.path().to_path_buf(),
+        ..Config::default()
+    }
+}
+
+fn item(title: &str, content: &str) -> IngestItem {
+    IngestItem {
+        title: title.to_string(),
+        content: content.to_string(),
+        source: Source {
+            name: "test".to_string(),
+            url: None,
+        },
+    }
+}
+
+async fn populate(config: &Config) {
+    let state = start(config).await;
+    ingest_items(
+        &state,
+        &[
+            item("rust", "async rust futures are polled by an executor"),
+            item("bread", "sourdough bread needs a starter"),
+        ],
+    )
+    .await;
+}
+
+#[actix_web::test]
+async fn repairs_unindexed_rows_and_orphan_vectors() {
+    let dir = TempDir::new().unwrap();
+    let config = config(&dir);
+    populate(&config).await;
+    assert!(diagnose(&config).await.is_healthy());
+
+    // A row whose indexing never happened, e.g. after a crash.
+    let row = content::ActiveModel {
+        id: ActiveValue::NotSet,
+        key: ActiveValue::Set(42),
+        created_at: ActiveValue::Set(Utc::now().to_rfc3339()),
+        title: ActiveValue::Set("kubernetes".to_string()),
+        text: ActiveValue::Set("kubernetes schedules pods".to_string()),
+        source: ActiveValue::Set("test".to_string()),
+        url: ActiveValue::Set(None),
+        embedding: ActiveValue::NotSet,
+        embedding_model: ActiveValue::NotSet,
+    }
+    .insert(&connect(&config).await)
+    .await
+    .unwrap();
+
+    // A vector whose row is gone.
+    let metadata = load_metadata(&config).unwrap();
+    let path = store_path(&config, &metadata, INDEX_NAME);
+    let mut store = open_store(&metadata, &path);
+    store.add(999, &vec![1.0; 64]);
+    store.persist(&path);
+
+    let diagnosis = diagnose(&config).await;
+    assert_eq!(diagnosis.unindexed, vec![row.key as u64]);
+    assert_eq!(diagnosis.orphans, vec![999]);
+    assert!(!diagnosis.is_healthy());
+
+    assert!(!repair(&config).await.is_healthy());
+
+    let diagnosis = diagnose(&config).await;
+    assert!(diagnosis.is_healthy(), "{}", diagnosis);
+    assert_eq!((diagnosis.rows, diagnosis.vectors), (3, 3));
+}
+
+#[actix_web::test]
+async fn rebuilds_missing_index() {
+    let dir = TempDir::new().unwrap();
+    let config = config(&dir);
+    populate(&config).await;
+
+    std::fs::remove_file(dir.path().join("index.usearch")).unwrap();
+    let diagnosis = diagnose(&config).await;
+    assert!(diagnosis.broken.is_some());
+    assert_eq!(diagnosis.unindexed.len(), 2);
+
+    repair(&config).await;
+
+    let diagnosis = diagnose(&config).await;
+    assert!(diagnosis.is_healthy(), "{}", diagnosis);
+    assert_eq!(diagnosis.vectors, 2);
+}
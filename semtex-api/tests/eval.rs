@@ -0,0 +1,53 @@
+use std::path::Path;
+
+use semtex_api::config::Config;
+use semtex_api::eval::{evaluate, read_dataset, score};
+
+fn ids(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+#[test]
+fn scores_ranking() {
+    let scores = score(&ids(&["a", "b", "c", "d"]), &ids(&["b", "d"]));
+
+    assert_eq!(scores.recall_1, 0.0);
+    assert_eq!(scores.recall_5, 1.0);
+    assert_eq!(scores.mrr, 0.5);
+    let dcg = 1.0 / 3f64.log2() + 1.0 / 5f64.log2();
+    let ideal = 1.0 + 1.0 / 3f64.log2();
+    assert!((scores.ndcg_10 - dcg / ideal).abs() < 1e-9);
+
+    let scores = score(&ids(&["a"]), &ids(&["z"]));
+    assert_eq!((scores.recall_10, scores.mrr, scores.ndcg_10), (0.0, 0.0, 0.0));
+}
+
+#[test]
+#[should_panic(expected = "dataset.jsonl:2: query has no relevant documents")]
+fn rejects_queries_without_relevant_documents() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("dataset.jsonl");
+    std::fs::write(
+        &path,
+        "{\"id\": \"a\", \"content\": \"text\"}\n{\"query\": \"text\", \"relevant\": []}\n",
+    )
+    .unwrap();
+    read_dataset(&path);
+}
+
+#[actix_web::test]
+async fn evaluates_sample_dataset() {
+    let dataset = read_dataset(&Path::new(env!("CARGO_MANIFEST_DIR")).join("eval/sample.jsonl"));
+    let config = Config {
+        model: "semtex/fake-256".to_string(),
+        embedding_workers: 1,
+        ..Config::default()
+    };
+
+    let report = evaluate("fake", &config, &dataset).await;
+
+    assert_eq!((report.documents, report.queries), (16, 8));
+    assert!(report.scores.mrr > 0.0 && report.scores.mrr <= 1.0);
+    assert!(report.scores.recall_10 <= 1.0);
+    assert!(report.scores.recall_1 <= report.scores.recall_5);
+}
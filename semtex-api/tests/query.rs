@@ -0,0 +1,88 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use semtex_api::query::Query;
+
+/// A Wednesday.
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 5, 15, 12, 0, 0).unwrap()
+}
+
+fn date(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap()
+}
+
+#[test]
+fn operators_are_taken_out_of_the_text() {
+    let query = Query::parse(
+        r#"rust site:GitHub.com source:notes "error handling" -unsafe -"async trait" lang:en"#,
+        now(),
+    );
+    assert_eq!(query.text, "rust lang:en");
+    assert_eq!(query.sites, ["github.com"]);
+    assert_eq!(query.sources, ["notes"]);
+    assert_eq!(query.phrases, ["error handling"]);
+    assert_eq!(query.excluded, ["unsafe", "async trait"]);
+    assert!(query.has_filters());
+}
+
+#[test]
+fn plain_text_has_no_filters() {
+    let query = Query::parse("  what is a monad  ", now());
+    assert_eq!(query.text, "what is a monad");
+    assert!(!query.has_filters());
+    assert_eq!(Query::parse("a - b", now()).text, "a - b");
+}
+
+#[test]
+fn date_operators_take_partial_and_natural_dates() {
+    let query = Query::parse("after:2023 before:2024-02", now());
+    assert_eq!(query.text, "");
+    assert_eq!(query.after, Some(date(2023, 1, 1)));
+    assert_eq!(query.before, Some(date(2024, 2, 1)));
+
+    let query = Query::parse(r#"after:"last week" before:yesterday"#, now());
+    assert_eq!(query.after, Some(date(2024, 5, 6)));
+    assert_eq!(query.before, Some(date(2024, 5, 14)));
+
+    // Not a date, so searched as text.
+    assert_eq!(Query::parse("after:lunch", now()).text, "after:lunch");
+    assert_eq!(Query::parse("before:2024-13", now()).before, None);
+}
+
+#[test]
+fn natural_dates_in_the_text_restrict_the_period() {
+    let query = Query::parse("notes from last month", now());
+    assert_eq!(query.text, "notes from");
+    assert_eq!(query.after, Some(date(2024, 4, 1)));
+    assert_eq!(query.before, Some(date(2024, 5, 1)));
+
+    let query = Query::parse("Today", now());
+    assert_eq!(query.text, "");
+    assert_eq!(query.after, Some(date(2024, 5, 15)));
+    assert_eq!(query.before, Some(now()));
+
+    let query = Query::parse("recipes past 3 days", now());
+    assert_eq!(query.text, "recipes");
+    assert_eq!(query.after, Some(date(2024, 5, 12) + Duration::hours(12)));
+
+    // The narrowest of several periods wins.
+    let query = Query::parse("this year after:2024-03", now());
+    assert_eq!(query.after, Some(date(2024, 3, 1)));
+}
+
+#[test]
+fn periods_out_of_range_are_left_as_text() {
+    for text in [
+        "past 9999999999999 days",
+        "last 2000000000000000000 weeks",
+        "past 100000000 days",
+    ] {
+        let query = Query::parse(text, now());
+        assert_eq!(query.text, text);
+        assert_eq!(query.after, None);
+    }
+    assert_eq!(
+        Query::parse("after:\"past 9999999999999 days\"", now()).after,
+        None
+    );
+    assert_eq!(Query::parse("after:\"past -5 days\"", now()).after, None);
+}
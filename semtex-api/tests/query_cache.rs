@@ -0,0 +1,66 @@
+use semtex_api::query_cache::QueryCache;
+use tempfile::TempDir;
+
+#[test]
+fn least_recently_used_query_is_evicted() {
+    let cache = QueryCache::new(2, None, "model");
+    cache.insert("model", "a", &[1.0]);
+    cache.insert("model", "b", &[2.0]);
+    assert_eq!(cache.get("model", "a"), Some(vec![1.0]));
+
+    cache.insert("model", "c", &[3.0]);
+    assert_eq!(cache.get("model", "b"), None);
+    assert_eq!(cache.get("model", "a"), Some(vec![1.0]));
+    assert_eq!(cache.get("model", "c"), Some(vec![3.0]));
+    // Another model's vector for the same query is a different entry.
+    assert_eq!(cache.get("other", "c"), None);
+
+    let stats = cache.stats();
+    assert_eq!(stats.entries, 2);
+    assert_eq!((stats.hits, stats.misses), (3, 2));
+    assert_eq!(stats.hit_rate, 0.6);
+}
+
+#[test]
+fn changing_model_drops_its_entries() {
+    let cache = QueryCache::new(8, None, "old");
+    cache.insert("old", "a", &[1.0]);
+    cache.insert("new", "a", &[2.0]);
+    cache.retain_model("new");
+    assert_eq!(cache.get("old", "a"), None);
+    assert_eq!(cache.get("new", "a"), Some(vec![2.0]));
+    assert_eq!(cache.stats().entries, 1);
+}
+
+#[test]
+fn zero_capacity_caches_nothing() {
+    let cache = QueryCache::new(0, None, "model");
+    cache.insert("model", "a", &[1.0]);
+    assert_eq!(cache.get("model", "a"), None);
+    assert_eq!(cache.stats().entries, 0);
+}
+
+#[test]
+fn persisted_cache_keeps_entries_of_the_same_model() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("query_cache.json");
+    let path = path.to_str().unwrap().to_string();
+
+    let cache = QueryCache::new(8, Some(path.clone()), "model");
+    cache.insert("model", "a", &[1.0, 2.0]);
+    cache.insert("other", "b", &[3.0]);
+    cache.save();
+
+    let reloaded = QueryCache::new(8, Some(path.clone()), "model");
+    assert_eq!(reloaded.get("model", "a"), Some(vec![1.0, 2.0]));
+    assert_eq!(reloaded.stats().entries, 1);
+    drop(reloaded);
+
+    // Unsaved entries are saved when the cache is dropped.
+    let cache = QueryCache::new(8, Some(path.clone()), "model");
+    cache.insert("model", "c", &[4.0]);
+    drop(cache);
+    let reloaded = QueryCache::new(8, Some(path), "model");
+    assert_eq!(reloaded.get("model", "c"), Some(vec![4.0]));
+    assert_eq!(reloaded.get("model", "a"), Some(vec![1.0, 2.0]));
+}
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+
+use semtex_api::config::RankingConfig;
+use semtex_api::ranking::{score, Signals};
+
+fn signals(similarity: f32, age_days: f32, visits: u32) -> Signals<'static> {
+    Signals {
+        similarity,
+        age_days,
+        visits,
+        source: "browser",
+    }
+}
+
+#[test]
+fn recency_halves_every_half_life() {
+    let config = RankingConfig {
+        recency_weight: 1.0,
+        half_life_days: 10.0,
+        ..RankingConfig::default()
+    };
+
+    assert_eq!(score(&config, &signals(0.5, 0.0, 0)).recency, 1.0);
+    assert_eq!(score(&config, &signals(0.5, 10.0, 0)).recency, 0.5);
+    assert_eq!(score(&config, &signals(0.5, 20.0, 0)).recency, 0.25);
+}
+
+#[test]
+fn total_blends_weighted_components() {
+    let config = RankingConfig {
+        similarity_weight: 2.0,
+        recency_weight: 0.0,
+        visits_weight: 1.0,
+        source_weights: HashMap::from([("browser".to_string(), 0.5)]),
+        ..RankingConfig::default()
+    };
+
+    let score = score(&config, &signals(0.5, 3.0, 3));
+    assert_eq!(score.similarity, 1.0);
+    assert_eq!(score.visits, 4f32.ln());
+    assert_eq!(score.source_weight, 0.5);
+    assert_eq!(score.total, (1.0 + 4f32.ln()) * 0.5);
+    assert_eq!((score.age_days, score.visit_count), (3.0, 3));
+}
+
+#[test]
+fn unlisted_sources_weigh_one() {
+    let score = score(&RankingConfig::default(), &signals(0.5, 0.0, 1));
+    assert_eq!(score.source_weight, 1.0);
+}
@@ -0,0 +1,82 @@
+use semtex_api::snippet::{candidates, contains_words, sentences, snippets, terms};
+
+const PAGE: &str = "Rust is a language.  It has no garbage collector!\nFutures in Rust do nothing until polled. Executors poll futures.";
+
+fn texts<'a>(text: &'a str, ranges: &[std::ops::Range<usize>]) -> Vec<&'a str> {
+    ranges.iter().map(|r| &text[r.clone()]).collect()
+}
+
+#[test]
+fn splits_sentences() {
+    assert_eq!(
+        texts(PAGE, &sentences(PAGE)),
+        [
+            "Rust is a language.",
+            "It has no garbage collector!",
+            "Futures in Rust do nothing until polled.",
+            "Executors poll futures.",
+        ]
+    );
+    assert_eq!(texts("version 1.2 is out", &sentences("version 1.2 is out")), ["version 1.2 is out"]);
+}
+
+#[test]
+fn finds_whole_words_only() {
+    assert!(contains_words(PAGE, "rust"));
+    assert!(contains_words(PAGE, "GARBAGE collector"));
+    assert!(!contains_words(PAGE, "lang"));
+    assert!(!contains_words(PAGE, "garbage futures"));
+    assert!(!contains_words("trust the compiler", "rust"));
+}
+
+#[test]
+fn cuts_overlong_sentences_at_spaces() {
+    let text = "word ".repeat(100);
+    let pieces = sentences(&text);
+    assert_eq!(pieces.len(), 2);
+    assert!(pieces.iter().all(|p| text[p.clone()].len() <= 300));
+    assert!(pieces.iter().all(|p| text[p.clone()].ends_with("word")));
+}
+
+#[test]
+fn picks_sentences_with_query_terms() {
+    let terms = terms("Rust futures");
+    let sentences = candidates(PAGE, &terms, 2);
+    assert_eq!(
+        texts(PAGE, &sentences),
+        ["Futures in Rust do nothing until polled.", "Rust is a language."]
+    );
+
+    let scored = sentences.into_iter().map(|s| (s, 0.0)).collect::<Vec<_>>();
+    let snippets = snippets(PAGE, &terms, &scored, 1);
+    assert_eq!(snippets.len(), 1);
+    assert_eq!(snippets[0].text, "Futures in Rust do nothing until polled.");
+    assert_eq!(snippets[0].offset, PAGE.find("Futures").unwrap());
+    assert_eq!(snippets[0].highlights, [0..7, 11..15]);
+}
+
+#[test]
+fn similarity_outweighs_fewer_terms() {
+    let terms = terms("rust");
+    let scored = sentences(PAGE)
+        .into_iter()
+        .map(|s| {
+            let similarity = if PAGE[s.clone()].starts_with("It has") { 1.0 } else { 0.0 };
+            (s, similarity)
+        })
+        .collect::<Vec<_>>();
+
+    let snippets = snippets(PAGE, &terms, &scored, 1);
+    assert_eq!(snippets[0].text, "It has no garbage collector!");
+    assert!(snippets[0].highlights.is_empty());
+}
+
+#[test]
+fn offsets_count_characters() {
+    let text = "Crème brûlée. Brûlée is burnt.";
+    let terms = terms("burnt");
+    let scored = sentences(text).into_iter().map(|s| (s, 0.0)).collect::<Vec<_>>();
+    let snippets = snippets(text, &terms, &scored, 1);
+    assert_eq!(snippets[0].offset, 14);
+    assert_eq!(snippets[0].highlights, vec![10..15]);
+}
@@ -0,0 +1,206 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use semtex_api::config::IndexConfig;
+use semtex_api::index::IndexMetadata;
+use semtex_api::store::{
+    discard_delta, open_store, try_open_store, view_store, Backend, SearchOptions,
+    VectorQuantization, VectorStore,
+};
+use tempfile::TempDir;
+
+const DIMENSIONS: usize = 32;
+
+fn open(dir: &TempDir, index: IndexConfig) -> Box<dyn VectorStore> {
+    let metadata = IndexMetadata::new("semtex/fake-32", DIMENSIONS, &index);
+    let path = dir.path().join(format!("index.{}", index.backend.extension()));
+    open_store(&metadata, path.to_str().unwrap())
+}
+
+fn backend(backend: Backend) -> IndexConfig {
+    IndexConfig {
+        backend,
+        ..IndexConfig::default()
+    }
+}
+
+fn random_vectors(rng: &mut StdRng, n: usize) -> Vec<Vec<f32>> {
+    (0..n)
+        .map(|_| (0..DIMENSIONS).map(|_| rng.gen_range(-1.0..1.0)).collect())
+        .collect()
+}
+
+#[test]
+fn exact_store_searches_removes_and_persists() {
+    let dir = TempDir::new().unwrap();
+    let mut store = open(&dir, backend(Backend::Exact));
+
+    let mut x = vec![0.0; DIMENSIONS];
+    x[0] = 1.0;
+    let mut y = vec![0.0; DIMENSIONS];
+    y[1] = 1.0;
+    let mut xy = vec![0.0; DIMENSIONS];
+    xy[0] = 1.0;
+    xy[1] = 1.0;
+
+    store.add(1, &x);
+    store.add(2, &y);
+    store.add(3, &xy);
+
+    let keys = |hits: Vec<semtex_api::store::Hit>| hits.iter().map(|h| h.key).collect::<Vec<_>>();
+    assert_eq!(keys(store.search(&x, &SearchOptions::top(10))), vec![1, 3, 2]);
+    let not_x = |key| key != 1;
+    let filtered = SearchOptions {
+        filter: Some(&not_x),
+        ..SearchOptions::top(10)
+    };
+    assert_eq!(keys(store.search(&x, &filtered)), vec![3, 2]);
+    assert!(store.search(&x, &SearchOptions::top(1))[0].distance.abs() < 1e-6);
+
+    assert!(store.remove(3));
+    assert!(!store.remove(3));
+    store.persist(dir.path().join("index.exact").to_str().unwrap());
+
+    let store = open(&dir, backend(Backend::Exact));
+    assert_eq!(store.stats().size, 2);
+    assert!(store.contains(2) && !store.contains(3));
+    assert_eq!(keys(store.search(&y, &SearchOptions::top(10))), vec![2, 1]);
+}
+
+#[test]
+fn truncated_exact_store_is_reported() {
+    let dir = TempDir::new().unwrap();
+    let mut store = open(&dir, backend(Backend::Exact));
+    store.add(1, &[0.5; DIMENSIONS]);
+    store.add(2, &[0.25; DIMENSIONS]);
+    let path = dir.path().join("index.exact");
+    store.persist(path.to_str().unwrap());
+
+    let metadata = IndexMetadata::new("semtex/fake-32", DIMENSIONS, &backend(Backend::Exact));
+    let bytes = std::fs::read(&path).unwrap();
+    let record = 8 + 4 * DIMENSIONS;
+    for cut in [4, 8 + record - 5, 8 + record + 3, bytes.len() - 2] {
+        std::fs::write(&path, &bytes[..cut]).unwrap();
+        let error = try_open_store(&metadata, path.to_str().unwrap())
+            .err()
+            .unwrap();
+        assert!(error.contains("index.exact"), "{}", error);
+    }
+}
+
+/// Fraction of the exact top 10 that usearch returns, over random queries.
+fn usearch_recall(index: IndexConfig, expansion_search: Option<usize>) -> f32 {
+    let dir = TempDir::new().unwrap();
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut exact = open(&dir, backend(Backend::Exact));
+    let mut usearch = open(&dir, index);
+
+    for (key, vector) in random_vectors(&mut rng, 1000).iter().enumerate() {
+        exact.add(key as u64, vector);
+        usearch.add(key as u64, vector);
+    }
+
+    let even = |key: u64| key & 1 == 0;
+    let mut found = 0;
+    let queries = random_vectors(&mut rng, 20);
+    for query in queries.iter() {
+        let expected = exact.search(query, &SearchOptions::top(10));
+        let actual = usearch.search(
+            query,
+            &SearchOptions {
+                expansion_search,
+                ..SearchOptions::top(10)
+            },
+        );
+        found += actual
+            .iter()
+            .filter(|hit| expected.iter().any(|e| e.key == hit.key))
+            .count();
+
+        let filtered = usearch.search(
+            query,
+            &SearchOptions {
+                filter: Some(&even),
+                ..SearchOptions::top(10)
+            },
+        );
+        assert_eq!(filtered.len(), 10);
+        assert!(filtered.iter().all(|hit| even(hit.key)));
+    }
+
+    found as f32 / (10 * queries.len()) as f32
+}
+
+#[test]
+fn usearch_agrees_with_exact_search() {
+    let recall = usearch_recall(backend(Backend::Usearch), None);
+    assert!(recall >= 0.9, "recall@10 {}", recall);
+
+    let tuned = IndexConfig {
+        connectivity: 32,
+        expansion_add: 256,
+        ..backend(Backend::Usearch)
+    };
+    let recall = usearch_recall(tuned, Some(256));
+    assert!(recall >= 0.95, "recall@10 {}", recall);
+}
+
+#[test]
+fn quantized_usearch_keeps_recall() {
+    for quantization in [VectorQuantization::F16, VectorQuantization::I8] {
+        let index = IndexConfig {
+            quantization,
+            ..backend(Backend::Usearch)
+        };
+        let recall = usearch_recall(index, None);
+        assert!(recall >= 0.8, "{:?} recall@10 {}", quantization, recall);
+    }
+}
+
+#[test]
+fn mapped_store_overlays_delta_on_mapped_index() {
+    let dir = TempDir::new().unwrap();
+    let mut rng = StdRng::seed_from_u64(3);
+    let vectors = random_vectors(&mut rng, 60);
+    let metadata = IndexMetadata::new("semtex/fake-32", DIMENSIONS, &IndexConfig::default());
+    let path = dir.path().join("index.usearch");
+    let path = path.to_str().unwrap();
+
+    let mut store = open_store(&metadata, path);
+    for (key, vector) in vectors[..50].iter().enumerate() {
+        store.add(key as u64, vector);
+    }
+    store.persist(path);
+
+    let mut mapped = view_store(&metadata, path);
+    for (key, vector) in vectors.iter().enumerate().skip(50) {
+        mapped.add(key as u64, vector);
+    }
+    // Replace vector 0 with vector 1's and remove vector 2.
+    mapped.add(0, &vectors[1]);
+    assert!(mapped.remove(2));
+    assert!(!mapped.remove(2));
+
+    let check = |store: &dyn VectorStore| {
+        assert_eq!(store.stats().size, 59);
+        assert!(store.contains(55) && !store.contains(2));
+        assert_eq!(store.get(0).unwrap(), vectors[1]);
+
+        let hits = store.search(&vectors[1], &SearchOptions::top(2));
+        let mut keys = hits.iter().map(|h| h.key).collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, vec![0, 1]);
+        assert_eq!(store.search(&vectors[55], &SearchOptions::top(1))[0].key, 55);
+        assert!(store.search(&vectors[2], &SearchOptions::top(60)).iter().all(|h| h.key != 2));
+    };
+    check(mapped.as_ref());
+    assert_eq!(mapped.stats().delta, 13);
+
+    mapped.persist(path);
+    drop(mapped);
+    check(view_store(&metadata, path).as_ref());
+
+    discard_delta(path);
+    let base = view_store(&metadata, path);
+    assert_eq!(base.stats().size, 50);
+    assert_eq!(base.stats().delta, 0);
+}
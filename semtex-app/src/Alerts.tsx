@@ -0,0 +1,138 @@
+import { useEffect, useState } from "react";
+import {
+  isPermissionGranted,
+  requestPermission,
+  sendNotification,
+} from "@tauri-apps/api/notification";
+
+const API = "http://localhost:8080";
+
+/** How often to check for new alerts. */
+const POLL_MS = 60_000;
+
+/** The newest alert a notification was sent for, across restarts. */
+const NOTIFIED_KEY = "alerts.notified";
+
+export interface Alert {
+  id: number;
+  saved_search_id: number;
+  name: string;
+  query: string;
+  key: string;
+  title: string;
+  url?: string;
+  similarity: number;
+  created_at: string;
+  seen: boolean;
+}
+
+async function notify(alerts: Alert[]) {
+  let granted = await isPermissionGranted();
+  if (!granted) {
+    granted = (await requestPermission()) === "granted";
+  }
+  if (!granted) {
+    return;
+  }
+  if (alerts.length === 1) {
+    sendNotification({ title: alerts[0].name, body: alerts[0].title });
+  } else {
+    const names = [...new Set(alerts.map((alert) => alert.name))];
+    sendNotification({
+      title: `${alerts.length} new matches`,
+      body: names.join(", "),
+    });
+  }
+}
+
+/**
+ * Unseen alerts for saved searches, with a desktop notification when new
+ * ones come in, and a list of them to open or mark as read.
+ */
+export function Alerts() {
+  const [alerts, setAlerts] = useState<Alert[]>([]);
+  // How many are unseen in all; only the newest are listed.
+  const [unseenCount, setUnseenCount] = useState(0);
+  const [open, setOpen] = useState(false);
+
+  async function load() {
+    const r = await fetch(`${API}/alerts?unseen=true`);
+    const response = await r.json();
+    const unseen: Alert[] = response["alerts"];
+    setAlerts(unseen);
+    setUnseenCount(response["unseen"]);
+
+    const notified = Number(localStorage.getItem(NOTIFIED_KEY) ?? 0);
+    const fresh = unseen.filter((alert) => alert.id > notified);
+    if (fresh.length > 0) {
+      localStorage.setItem(NOTIFIED_KEY, String(fresh[0].id));
+      notify(fresh);
+    }
+  }
+
+  useEffect(() => {
+    load();
+    const timer = setInterval(load, POLL_MS);
+    return () => clearInterval(timer);
+  }, []);
+
+  async function markSeen(ids?: number[]) {
+    await fetch(`${API}/alerts/seen`, {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify(ids ? { ids } : {}),
+    });
+    load();
+  }
+
+  if (alerts.length === 0) {
+    return null;
+  }
+
+  return (
+    <div className="relative text-sm">
+      <button
+        className="rounded-full bg-red-600 px-2 py-0.5 font-semibold text-white shadow-sm hover:bg-red-500"
+        onClick={() => setOpen(!open)}
+      >
+        {unseenCount} new
+      </button>
+
+      {open && (
+        <div className="absolute right-0 mt-2 w-96 rounded-md bg-white shadow-lg ring-1 ring-gray-300">
+          <ul className="max-h-96 divide-y divide-gray-200 overflow-y-auto">
+            {alerts.map((alert) => (
+              <li key={alert.id} className="flex items-center gap-3 px-3 py-1">
+                <div className="min-w-0 flex-1">
+                  <a
+                    href={alert.url}
+                    target="_blank"
+                    className="block truncate font-semibold hover:underline"
+                    onClick={() => markSeen([alert.id])}
+                  >
+                    {alert.title}
+                  </a>
+                  <p className="truncate text-xs text-gray-500">
+                    {alert.name} · {Math.round(alert.similarity * 100)}%
+                  </p>
+                </div>
+                <button
+                  className="text-indigo-600 hover:underline"
+                  onClick={() => markSeen([alert.id])}
+                >
+                  read
+                </button>
+              </li>
+            ))}
+          </ul>
+          <button
+            className="w-full border-t border-gray-200 px-3 py-1 text-left text-indigo-600 hover:underline"
+            onClick={() => markSeen()}
+          >
+            mark all read
+          </button>
+        </div>
+      )}
+    </div>
+  );
+}
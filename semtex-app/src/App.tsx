@@ -1,15 +1,54 @@
 import { useState } from "react";
+import { Alerts } from "./Alerts";
 import { ResultList } from "./ResultList";
+import { SavedSearch, SavedSearches, saveSearch } from "./SavedSearches";
 
 function App() {
   const [name, setName] = useState("");
   const [data, setData] = useState<any[]>([]);
+  // The id of the search in the history, if it was recorded.
+  const [searchId, setSearchId] = useState<number | null>(null);
+  const [savedVersion, setSavedVersion] = useState(0);
 
   async function search() {
-    const r = await fetch(`http://localhost:8080/search?query=${name}`, {
+    const r = await fetch(
+      `http://localhost:8080/search?query=${encodeURIComponent(name)}`,
+      {
+        headers: { "Content-Type": "application/json" },
+      }
+    );
+    showResults(await r.json());
+  }
+
+  async function runSaved(saved: SavedSearch) {
+    setName(saved.query);
+    const r = await fetch(
+      `http://localhost:8080/saved-searches/${saved.id}/results`
+    );
+    showResults(await r.json());
+  }
+
+  function showResults(response: any) {
+    setData(response["results"]);
+    setSearchId(response["search_id"] ?? null);
+  }
+
+  async function save() {
+    if (name.trim() && (await saveSearch(name))) {
+      setSavedVersion(savedVersion + 1);
+    }
+  }
+
+  /** Records in the history that a result was opened. */
+  function recordClick(key: string) {
+    if (searchId === null) {
+      return;
+    }
+    fetch(`http://localhost:8080/history/${searchId}/clicks`, {
+      method: "POST",
       headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({ key }),
     });
-    setData((await r.json())["results"]);
   }
 
   return (
@@ -33,6 +72,7 @@ function App() {
                   id="username"
                   placeholder="Something cool..."
                   className="block flex-1 border-0 bg-transparent py-1.5 pl-1 text-gray-900 placeholder:text-gray-400 focus:ring-0 sm:text-sm sm:leading-6"
+                  value={name}
                   onChange={(e) => setName(e.currentTarget.value)}
                 />
               </div>
@@ -42,13 +82,24 @@ function App() {
               >
                 Search
               </button>
+              <button
+                className="rounded bg-white px-2 py-1 text-sm font-semibold text-gray-900 shadow-sm ring-1 ring-inset ring-gray-300 hover:bg-gray-50"
+                type="button"
+                onClick={save}
+              >
+                Save
+              </button>
             </div>
           </form>
+          <div className="ml-2">
+            <Alerts />
+          </div>
         </div>
+        <SavedSearches version={savedVersion} onRun={runSaved} />
       </div>
 
       <div className="container mx-auto px-2 py-4 z-0">
-        {data && <ResultList results={data} />}
+        {data && <ResultList results={data} onOpen={recordClick} />}
       </div>
     </>
   );
@@ -1,3 +1,5 @@
+import { useState } from "react";
+
 // const people = [
 //   {
 //     name: 'Leslie Alexander',
@@ -59,7 +61,58 @@
 //   },
 // ]
 
-export function ResultList({ results }: Props) {
+/** Splits `text` at the highlighted character ranges. */
+function Highlighted({ snippet }: { snippet: Snippet }) {
+  // Offsets count characters, not UTF-16 code units.
+  const chars = Array.from(snippet.text);
+  const parts = [];
+  let end = 0;
+  for (const highlight of snippet.highlights) {
+    parts.push(chars.slice(end, highlight.start).join(""));
+    parts.push(
+      <mark key={highlight.start} className="bg-yellow-200">
+        {chars.slice(highlight.start, highlight.end).join("")}
+      </mark>
+    );
+    end = highlight.end;
+  }
+  parts.push(chars.slice(end).join(""));
+  return <>{parts}</>;
+}
+
+/** The snippets of a result, or its full text once asked for. */
+function ResultText({ result }: { result: Result }) {
+  const [text, setText] = useState<string | null>(null);
+
+  async function showFullText() {
+    const r = await fetch(`http://localhost:8080/content/${result.key}`);
+    setText((await r.json())["text"]);
+  }
+
+  if (text !== null) {
+    return (
+      <p className="whitespace-pre-wrap text-xs text-gray-700 bg-gray-100 rounded-md p-1">
+        {text}
+      </p>
+    );
+  }
+
+  return (
+    <p className="text-xs text-gray-700 bg-gray-100 rounded-md p-1">
+      {result.snippets.map((snippet, i) => (
+        <span key={snippet.offset}>
+          {(i > 0 || snippet.offset > 0) && "… "}
+          <Highlighted snippet={snippet} />{" "}
+        </span>
+      ))}
+      <button className="text-indigo-600 hover:underline" onClick={showFullText}>
+        more
+      </button>
+    </p>
+  );
+}
+
+export function ResultList({ results, onOpen }: Props) {
   return (
     <ul role="list" className="divide-y divide-gray-100">
       {results.map((result) => (
@@ -70,13 +123,16 @@ export function ResultList({ results }: Props) {
           <div className="flex min-w-0 gap-x-4">
             <div className="min-w-0 flex-auto">
               <p className="text-sm font-semibold leading-6 text-gray-900">
-                <a href={result.url} target="_blank" className="flex items-center gap-2">
+                <a
+                  href={result.url}
+                  target="_blank"
+                  className="flex items-center gap-2"
+                  onClick={() => onOpen?.(result.key)}
+                >
                   {result.title} <span className="text-xs text-gray-500 truncate block max-w-64">{result.url}</span>
                 </a>
               </p>
-              <p className="line-clamp-3 text-xs text-gray-700 bg-gray-100 rounded-md p-1">
-                {result.text}
-              </p>
+              <ResultText result={result} />
             </div>
           </div>
           <div className="flex shrink-0 items-center gap-x-4">
@@ -99,12 +155,24 @@ export function ResultList({ results }: Props) {
   );
 }
 
+interface Snippet {
+  text: string;
+  /** Characters of the page before `text`. */
+  offset: number;
+  /** Character ranges of query terms within `text`. */
+  highlights: { start: number; end: number }[];
+}
+
+interface Result {
+  key: string;
+  title: string;
+  snippets: Snippet[];
+  url: string;
+  distance: number;
+}
+
 interface Props {
-  results: {
-    key: string;
-    title: string;
-    text: string;
-    url: string;
-    distance: number;
-  }[];
+  results: Result[];
+  /** Called with the key of a result when its link is opened. */
+  onOpen?: (key: string) => void;
 }
\ No newline at end of file
@@ -0,0 +1,154 @@
+import { useEffect, useState } from "react";
+
+const API = "http://localhost:8080";
+
+export interface SavedSearch {
+  id: number;
+  name: string;
+  query: string;
+  pinned: boolean;
+  created_at: string;
+  /** Whether newly indexed pages that match raise alerts. */
+  alert: boolean;
+  alert_threshold?: number;
+}
+
+async function update(id: number, changes: Partial<SavedSearch>) {
+  await fetch(`${API}/saved-searches/${id}`, {
+    method: "PUT",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(changes),
+  });
+}
+
+/** Saves `query` under a name asked for, returning whether it was saved. */
+export async function saveSearch(query: string) {
+  const name = window.prompt("Name this search", query);
+  if (!name) {
+    return false;
+  }
+  await fetch(`${API}/saved-searches`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ name, query, pinned: true }),
+  });
+  return true;
+}
+
+/**
+ * Pinned searches as buttons that run them, and a list of all saved searches
+ * to pin, unpin, rename or delete. `version` changes when a search is saved
+ * elsewhere.
+ */
+export function SavedSearches({ version, onRun }: Props) {
+  const [saved, setSaved] = useState<SavedSearch[]>([]);
+  const [open, setOpen] = useState(false);
+
+  async function load() {
+    const r = await fetch(`${API}/saved-searches`);
+    setSaved((await r.json())["saved_searches"]);
+  }
+
+  useEffect(() => {
+    load();
+  }, [version]);
+
+  async function rename(search: SavedSearch) {
+    const name = window.prompt("Rename search", search.name);
+    if (name) {
+      await update(search.id, { name });
+      load();
+    }
+  }
+
+  async function togglePin(search: SavedSearch) {
+    await update(search.id, { pinned: !search.pinned });
+    load();
+  }
+
+  async function toggleAlert(search: SavedSearch) {
+    await update(search.id, { alert: !search.alert });
+    load();
+  }
+
+  async function remove(search: SavedSearch) {
+    await fetch(`${API}/saved-searches/${search.id}`, { method: "DELETE" });
+    load();
+  }
+
+  if (saved.length === 0) {
+    return null;
+  }
+
+  return (
+    <div className="mt-2 text-sm">
+      <div className="flex flex-wrap items-center gap-2">
+        {saved
+          .filter((search) => search.pinned)
+          .map((search) => (
+            <button
+              key={search.id}
+              className="rounded-full bg-white px-3 py-0.5 shadow-sm ring-1 ring-gray-300 hover:bg-indigo-50"
+              title={search.query}
+              onClick={() => onRun(search)}
+            >
+              {search.name}
+            </button>
+          ))}
+        <button
+          className="text-indigo-600 hover:underline"
+          onClick={() => setOpen(!open)}
+        >
+          {open ? "hide saved" : "all saved"}
+        </button>
+      </div>
+
+      {open && (
+        <ul className="mt-2 divide-y divide-gray-200 rounded-md bg-white shadow-sm ring-1 ring-gray-300">
+          {saved.map((search) => (
+            <li key={search.id} className="flex items-center gap-3 px-3 py-1">
+              <button
+                className="flex-1 truncate text-left hover:underline"
+                onClick={() => onRun(search)}
+              >
+                <span className="font-semibold">{search.name}</span>{" "}
+                <span className="text-gray-500">{search.query}</span>
+              </button>
+              <button
+                className="text-indigo-600 hover:underline"
+                onClick={() => togglePin(search)}
+              >
+                {search.pinned ? "unpin" : "pin"}
+              </button>
+              <button
+                className="text-indigo-600 hover:underline"
+                title="Alert when newly indexed pages match"
+                onClick={() => toggleAlert(search)}
+              >
+                {search.alert ? "unwatch" : "watch"}
+              </button>
+              <button
+                className="text-indigo-600 hover:underline"
+                onClick={() => rename(search)}
+              >
+                rename
+              </button>
+              <button
+                className="text-red-600 hover:underline"
+                onClick={() => remove(search)}
+              >
+                delete
+              </button>
+            </li>
+          ))}
+        </ul>
+      )}
+    </div>
+  );
+}
+
+interface Props {
+  /** Changes whenever the saved searches need to be fetched again. */
+  version: number;
+  onRun: (search: SavedSearch) => void;
+}
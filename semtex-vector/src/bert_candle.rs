@@ -0,0 +1,185 @@
+use candle_core::{DType, Device, Error, Result, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config};
+use hf_hub::{api::sync::Api, Repo, RepoType};
+use tokenizers::{Tokenizer, TruncationParams};
+
+use crate::{
+    embedding::EmbeddingModel,
+    quantized_bert::{quantize_safetensors, Quantization, QuantizedBertModel},
+    util::{device, hub_load_safetensors_files, BertTokens},
+};
+
+/// How token embeddings are reduced to a single sentence embedding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pooling {
+    Cls,
+    Mean,
+}
+
+enum Encoder {
+    Full(BertModel),
+    Quantized(QuantizedBertModel),
+}
+
+impl Encoder {
+    fn forward(&self, input_ids: &Tensor, token_type_ids: &Tensor) -> Result<Tensor> {
+        match self {
+            Encoder::Full(model) => model.forward(input_ids, token_type_ids),
+            Encoder::Quantized(model) => model.forward(input_ids, token_type_ids),
+        }
+    }
+
+    fn device(&self) -> &Device {
+        match self {
+            Encoder::Full(model) => &model.device,
+            Encoder::Quantized(model) => &model.device,
+        }
+    }
+}
+
+/// A BERT-architecture sentence embedding model from the hub, run with candle.
+///
+/// Asymmetric models (BGE, E5) are trained with different instructions for
+/// queries and passages; these are applied by `embed_query` / `embed_documents`.
+pub struct BertCandle {
+    model_id: String,
+    dimensions: usize,
+    model: Encoder,
+    tokenizer: Tokenizer,
+    pooling: Pooling,
+    query_prefix: String,
+    document_prefix: String,
+}
+
+impl BertCandle {
+    pub const BGE_SMALL: &'static str = "BAAI/bge-small-en-v1.5";
+    pub const E5_SMALL: &'static str = "intfloat/e5-small-v2";
+
+    pub fn bge(model_id: &str, quantization: Option<Quantization>) -> Result<BertCandle> {
+        BertCandle::new(
+            model_id,
+            Pooling::Cls,
+            "Represent this sentence for searching relevant passages: ",
+            "",
+            quantization,
+        )
+    }
+
+    pub fn e5(model_id: &str, quantization: Option<Quantization>) -> Result<BertCandle> {
+        BertCandle::new(model_id, Pooling::Mean, "query: ", "passage: ", quantization)
+    }
+
+    /// With `quantization`, the linear layers run on quantized weights, which
+    /// are converted from the safetensors weights on first use and cached.
+    pub fn new(
+        model_id: &str,
+        pooling: Pooling,
+        query_prefix: &str,
+        document_prefix: &str,
+        quantization: Option<Quantization>,
+    ) -> Result<BertCandle> {
+        let device = device(true)?;
+
+        let api = Api::new().map_err(Error::wrap)?;
+        let repo = api.repo(Repo::new(model_id.to_string(), RepoType::Model));
+
+        let tokenizer = repo.get("tokenizer.json").map_err(Error::wrap)?;
+        let mut tokenizer = Tokenizer::from_file(tokenizer).map_err(|e| Error::Msg(e.to_string()))?;
+        tokenizer
+            .with_padding(None)
+            .with_truncation(Some(TruncationParams {
+                max_length: 512,
+                ..Default::default()
+            }))
+            .map_err(|e| Error::Msg(e.to_string()))?;
+
+        let filenames = hub_load_safetensors_files(&repo, &["model.safetensors"])?;
+
+        let config_filename = repo.get("config.json").map_err(Error::wrap)?;
+        let config = std::fs::read_to_string(config_filename)?;
+        let hidden_size = serde_json::from_str::<serde_json::Value>(&config)
+            .map_err(Error::wrap)?["hidden_size"]
+            .as_u64()
+            .unwrap_or(384);
+
+        let model = match quantization {
+            None => {
+                let vb = unsafe {
+                    VarBuilder::from_mmaped_safetensors(&filenames, DType::F32, &device)?
+                };
+                let config: Config = serde_json::from_str(&config).map_err(Error::wrap)?;
+                Encoder::Full(BertModel::load(vb, &config)?)
+            }
+            Some(quantization) => {
+                let gguf = quantize_safetensors(&filenames[0], quantization)?;
+                Encoder::Quantized(QuantizedBertModel::load(&gguf, &config)?)
+            }
+        };
+
+        Ok(BertCandle {
+            model_id: match quantization {
+                None => model_id.to_string(),
+                Some(quantization) => format!("{}:{}", model_id, quantization.name()),
+            },
+            dimensions: hidden_size as usize,
+            model,
+            tokenizer,
+            pooling,
+            query_prefix: query_prefix.to_string(),
+            document_prefix: document_prefix.to_string(),
+        })
+    }
+
+    fn embed_one(&self, tokens: &BertTokens) -> Result<Vec<f32>> {
+        let input_ids = Tensor::new(tokens.encoding.get_ids(), self.model.device())?.unsqueeze(0)?;
+        let token_type_ids = input_ids.zeros_like()?;
+        let embeddings = self.model.forward(&input_ids, &token_type_ids)?;
+
+        let pooled = match self.pooling {
+            Pooling::Cls => embeddings.get(0)?.get(0)?,
+            Pooling::Mean => embeddings.get(0)?.mean(0)?,
+        };
+        let pooled = pooled.broadcast_div(&pooled.sqr()?.sum_all()?.sqrt()?)?;
+
+        pooled.to_vec1()
+    }
+}
+
+impl EmbeddingModel for BertCandle {
+    type TokenizedOutput = BertTokens;
+
+    fn tokenize(self: &Self, text: &[&str]) -> Vec<BertTokens> {
+        self.tokenizer
+            .encode_batch(text.to_vec(), true)
+            .unwrap()
+            .into_iter()
+            .map(|encoding| BertTokens { encoding })
+            .collect::<Vec<_>>()
+    }
+
+    // The candle BERT implementation has no attention mask, so inputs are run
+    // one at a time rather than padded into a batch.
+    fn embed(self: &Self, tokenized_output: Vec<BertTokens>) -> Vec<Vec<f32>> {
+        tokenized_output
+            .iter()
+            .map(|tokens| self.embed_one(tokens).unwrap())
+            .collect::<Vec<_>>()
+    }
+
+    fn model_id(self: &Self) -> &str {
+        &self.model_id
+    }
+
+    fn dimensions(self: &Self) -> usize {
+        self.dimensions
+    }
+
+    fn query_prefix(self: &Self) -> &str {
+        &self.query_prefix
+    }
+
+    fn document_prefix(self: &Self) -> &str {
+        &self.document_prefix
+    }
+}
@@ -0,0 +1,92 @@
+use candle_core::{DType, Error, Result, Tensor};
+use candle_nn::{Linear, Module, VarBuilder};
+use candle_transformers::models::bert::{BertModel, Config};
+use hf_hub::{api::sync::Api, Repo, RepoType};
+use tokenizers::{Tokenizer, TruncationParams};
+
+use crate::util::{device, hub_load_safetensors_files};
+
+/// Scores how well passages answer a query by reading both together, which
+/// is slower but more precise than comparing embeddings.
+pub trait Reranker: Send + Sync {
+    fn model_id(&self) -> &str;
+    /// Relevance of `passage` to `query`; higher is more relevant. Scores are
+    /// only comparable between passages for the same query.
+    fn score(&self, query: &str, passage: &str) -> Result<f32>;
+}
+
+/// A BERT cross-encoder with a single-logit classification head, such as the
+/// ms-marco MiniLM rerankers, run with candle.
+pub struct CrossEncoder {
+    model_id: String,
+    model: BertModel,
+    pooler: Linear,
+    classifier: Linear,
+    tokenizer: Tokenizer,
+}
+
+impl CrossEncoder {
+    pub const MS_MARCO_MINILM: &'static str = "cross-encoder/ms-marco-MiniLM-L-6-v2";
+
+    pub fn new(model_id: &str) -> Result<CrossEncoder> {
+        let device = device(true)?;
+
+        let api = Api::new().map_err(Error::wrap)?;
+        let repo = api.repo(Repo::new(model_id.to_string(), RepoType::Model));
+
+        let tokenizer = repo.get("tokenizer.json").map_err(Error::wrap)?;
+        let mut tokenizer = Tokenizer::from_file(tokenizer).map_err(|e| Error::Msg(e.to_string()))?;
+        tokenizer
+            .with_padding(None)
+            .with_truncation(Some(TruncationParams {
+                max_length: 512,
+                ..Default::default()
+            }))
+            .map_err(|e| Error::Msg(e.to_string()))?;
+
+        let filenames = hub_load_safetensors_files(&repo, &["model.safetensors"])?;
+        let config_filename = repo.get("config.json").map_err(Error::wrap)?;
+        let config = std::fs::read_to_string(config_filename)?;
+        let hidden_size = serde_json::from_str::<serde_json::Value>(&config)
+            .map_err(Error::wrap)?["hidden_size"]
+            .as_u64()
+            .unwrap_or(384) as usize;
+        let config: Config = serde_json::from_str(&config).map_err(Error::wrap)?;
+
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&filenames, DType::F32, &device)? };
+
+        Ok(CrossEncoder {
+            model_id: model_id.to_string(),
+            model: BertModel::load(vb.pp("bert"), &config)?,
+            pooler: candle_nn::linear(hidden_size, hidden_size, vb.pp("bert.pooler.dense"))?,
+            classifier: candle_nn::linear(hidden_size, 1, vb.pp("classifier"))?,
+            tokenizer,
+        })
+    }
+
+    fn score_pair(&self, query: &str, passage: &str) -> Result<f32> {
+        let encoding = self
+            .tokenizer
+            .encode((query, passage), true)
+            .map_err(|e| Error::Msg(e.to_string()))?;
+
+        let input_ids = Tensor::new(encoding.get_ids(), &self.model.device)?.unsqueeze(0)?;
+        let token_type_ids =
+            Tensor::new(encoding.get_type_ids(), &self.model.device)?.unsqueeze(0)?;
+        let hidden = self.model.forward(&input_ids, &token_type_ids)?;
+
+        let cls = hidden.get(0)?.get(0)?.unsqueeze(0)?;
+        let pooled = self.pooler.forward(&cls)?.tanh()?;
+        self.classifier.forward(&pooled)?.squeeze(0)?.squeeze(0)?.to_scalar()
+    }
+}
+
+impl Reranker for CrossEncoder {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    fn score(&self, query: &str, passage: &str) -> Result<f32> {
+        self.score_pair(query, passage)
+    }
+}
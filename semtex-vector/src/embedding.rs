@@ -3,6 +3,20 @@ pub trait EmbeddingModel {
     type TokenizedOutput: TokenizedOutput;
     fn tokenize(self: &Self, text: &[&str]) -> Vec<Self::TokenizedOutput>;
     fn embed(self: &Self, tokenized_output: Vec<Self::TokenizedOutput>) -> Vec<Vec<f32>>;
+
+    /// Hub id of the model, recorded alongside the vectors it produces.
+    fn model_id(self: &Self) -> &str;
+    fn dimensions(self: &Self) -> usize;
+
+    /// Instruction prepended to search queries. Empty for symmetric models.
+    fn query_prefix(self: &Self) -> &str {
+        ""
+    }
+
+    /// Instruction prepended to indexed documents. Empty for symmetric models.
+    fn document_prefix(self: &Self) -> &str {
+        ""
+    }
 }
 
 pub trait  TokenizedOutput { }
@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+
+use crate::cross_encoder::Reranker;
+use crate::embedding::{EmbeddingModel, TokenizedOutput};
+
+/// Deterministic stand-in for a real model: a hashed bag of words, for tests
+/// that must not download weights. Texts sharing words are close; texts with
+/// none in common are (almost) orthogonal.
+pub struct FakeEmbedder {
+    model_id: String,
+    dimensions: usize,
+}
+
+pub struct Words {
+    words: Vec<String>,
+}
+
+impl TokenizedOutput for Words {}
+
+impl FakeEmbedder {
+    /// Model ids are `semtex/fake-<dimensions>`, e.g. `semtex/fake-64`.
+    pub const MODEL_PREFIX: &'static str = "semtex/fake-";
+
+    pub fn new(dimensions: usize) -> FakeEmbedder {
+        FakeEmbedder {
+            model_id: format!("{}{}", FakeEmbedder::MODEL_PREFIX, dimensions),
+            dimensions,
+        }
+    }
+
+    /// Parses the dimensions out of a `semtex/fake-<dimensions>` id.
+    pub fn from_model_id(model_id: &str) -> Option<FakeEmbedder> {
+        model_id
+            .strip_prefix(FakeEmbedder::MODEL_PREFIX)
+            .and_then(|dimensions| dimensions.parse().ok())
+            .map(FakeEmbedder::new)
+    }
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+/// FNV-1a, so that vectors are stable across runs and toolchains.
+fn hash(word: &str) -> u64 {
+    word.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl EmbeddingModel for FakeEmbedder {
+    type TokenizedOutput = Words;
+
+    fn tokenize(self: &Self, text: &[&str]) -> Vec<Words> {
+        text.iter()
+            .map(|s| Words { words: words(s) })
+            .collect::<Vec<_>>()
+    }
+
+    fn embed(self: &Self, tokenized_output: Vec<Words>) -> Vec<Vec<f32>> {
+        tokenized_output
+            .iter()
+            .map(|tokens| {
+                let mut embedding = vec![0.0; self.dimensions];
+                for word in tokens.words.iter() {
+                    let hash = hash(word);
+                    let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+                    embedding[(hash % self.dimensions as u64) as usize] += sign;
+                }
+
+                let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+                if norm > 0.0 {
+                    embedding.iter_mut().for_each(|x| *x /= norm);
+                }
+                embedding
+            })
+            .collect::<Vec<_>>()
+    }
+
+    fn model_id(self: &Self) -> &str {
+        &self.model_id
+    }
+
+    fn dimensions(self: &Self) -> usize {
+        self.dimensions
+    }
+}
+
+/// Deterministic stand-in for a cross-encoder: scores a passage by the share
+/// of distinct query words it contains, however often and among however many
+/// other words, so it can disagree with `FakeEmbedder`.
+pub struct FakeReranker;
+
+impl FakeReranker {
+    pub const MODEL_ID: &'static str = "semtex/fake-reranker";
+}
+
+impl Reranker for FakeReranker {
+    fn model_id(&self) -> &str {
+        FakeReranker::MODEL_ID
+    }
+
+    fn score(&self, query: &str, passage: &str) -> candle_core::Result<f32> {
+        let query = words(query).into_iter().collect::<HashSet<_>>();
+        let passage = words(passage).into_iter().collect::<HashSet<_>>();
+        if query.is_empty() {
+            return Ok(0.0);
+        }
+        Ok(query.intersection(&passage).count() as f32 / query.len() as f32)
+    }
+}
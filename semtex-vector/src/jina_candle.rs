@@ -2,7 +2,7 @@ use std::time::Instant;
 
 use candle_core::{DType, Error, Module, Result, Tensor};
 use candle_nn::VarBuilder;
-use candle_transformers::models::jina_bert::BertModel as JinaModel;
+use candle_transformers::models::jina_bert::{BertModel as JinaModel, Config};
 use hf_hub::{api::sync::Api, Repo, RepoType};
 use tokenizers::Tokenizer;
 
@@ -15,12 +15,15 @@ use crate::{
 pub struct JinaCandle {
     model: JinaModel,
     tokenizer: Tokenizer,
+    dimensions: usize,
 }
 
 impl JinaCandle {
+    pub const MODEL_ID: &'static str = "jinaai/jina-embeddings-v2-small-en";
+
     pub fn new() -> Result<JinaCandle> {
         let device = device(true)?;
-        let model_id = "jinaai/jina-embeddings-v2-small-en".to_string();
+        let model_id = JinaCandle::MODEL_ID.to_string();
 
         let api = Api::new().map_err(Error::wrap)?;
         let repo = api.repo(Repo::new(model_id, RepoType::Model));
@@ -42,12 +45,13 @@ impl JinaCandle {
 
         let config_filename = repo.get("config.json").unwrap();
         let config = std::fs::read_to_string(config_filename)?;
-        let config = serde_json::from_str(&config).unwrap();
+        let config: Config = serde_json::from_str(&config).unwrap();
 
         let model = JinaModel::new(vb, &config).unwrap();
         Ok(JinaCandle {
             model: model,
             tokenizer: tokenizer,
+            dimensions: config.hidden_size,
         })
     }
 }
@@ -86,6 +90,14 @@ impl EmbeddingModel for JinaCandle {
 
         embeddings.to_vec2().unwrap()
     }
+
+    fn model_id(self: &Self) -> &str {
+        JinaCandle::MODEL_ID
+    }
+
+    fn dimensions(self: &Self) -> usize {
+        self.dimensions
+    }
 }
 
 fn normalize_l2(v: &Tensor) -> candle_core::Result<Tensor> {
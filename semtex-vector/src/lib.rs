@@ -1,45 +1,133 @@
+pub mod bert_candle;
+pub mod cross_encoder;
 pub mod embedding;
+#[cfg(feature = "fake")]
+pub mod fake;
 pub mod jina_candle;
+pub mod long_text;
 pub mod minilm;
+pub mod quantized_bert;
 mod util;
 
+use bert_candle::{BertCandle, Pooling};
+use cross_encoder::{CrossEncoder, Reranker};
 use embedding::{EmbeddingModel, TokenizedOutput};
 use jina_candle::JinaCandle;
+use minilm::MiniLM;
+use quantized_bert::Quantization;
 
-// pub fn build_model_and_tokenizer() -> candle_core::Result<LoadedModel> {
-//     let device = device(true)?;
-//     let model_id = "BAAI/bge-base-en-v1.5".to_string();
-//     let revision = "main".to_string();
+pub fn embed<T: TokenizedOutput>(
+    model: &mut dyn EmbeddingModel<TokenizedOutput = T>,
+    text: &[&str],
+) -> Vec<Vec<f32>> {
+    let tokenized_output = model.tokenize(text);
+    return model.embed(tokenized_output);
+}
 
-//     let api = Api::new().map_err(Error::wrap)?;
-//     let repo = api.repo(Repo::with_revision(model_id, RepoType::Model, revision));
+/// Embeds a search query, applying the model's query instruction.
+pub fn embed_query<T: TokenizedOutput>(
+    model: &dyn EmbeddingModel<TokenizedOutput = T>,
+    query: &str,
+) -> Vec<f32> {
+    let text = format!("{}{}", model.query_prefix(), query);
+    let tokenized_output = model.tokenize(&[&text]);
+    model.embed(tokenized_output).remove(0)
+}
 
-//     let config_filename = repo.get("config.json").unwrap();
-//     let tokenizer = repo.get("tokenizer.json").unwrap();
-//     let tokenizer = Tokenizer::from_file(tokenizer).unwrap();
+/// Embeds documents for indexing, applying the model's document instruction.
+pub fn embed_documents<T: TokenizedOutput>(
+    model: &dyn EmbeddingModel<TokenizedOutput = T>,
+    documents: &[&str],
+) -> Vec<Vec<f32>> {
+    let texts = documents
+        .iter()
+        .map(|d| format!("{}{}", model.document_prefix(), d))
+        .collect::<Vec<_>>();
+    let tokenized_output = model.tokenize(&texts.iter().map(|t| t.as_str()).collect::<Vec<_>>());
+    model.embed(tokenized_output)
+}
 
-//     let filenames = hub_load_safetensors(&repo, "model.safetensors.index.json").unwrap();
-//     filenames
-//         .iter()
-//         .for_each(|fname| println!("File: {:?}", fname.to_str()));
-//     let vb =
-//         unsafe { VarBuilder::from_mmaped_safetensors(&filenames, DType::F32, &device).unwrap() };
-//     let config = std::fs::read_to_string(config_filename)?;
-//     let config = serde_json::from_str(&config).unwrap();
+/// Object-safe wrapper over `EmbeddingModel` so the model can be chosen at runtime.
+pub trait Embedder: Send + Sync {
+    fn model_id(&self) -> &str;
+    fn dimensions(&self) -> usize;
+    fn embed_query(&self, query: &str) -> Vec<f32>;
+    fn embed_documents(&self, documents: &[&str]) -> Vec<Vec<f32>>;
+}
 
-//     let model = BertModel::load(vb, &config).unwrap();
-//     Ok(LoadedModel {
-//         model: model,
-//         tokenizer: tokenizer,
-//     })
-// }
+impl<M: EmbeddingModel + Send + Sync> Embedder for M {
+    fn model_id(&self) -> &str {
+        EmbeddingModel::model_id(self)
+    }
 
+    fn dimensions(&self) -> usize {
+        EmbeddingModel::dimensions(self)
+    }
 
+    fn embed_query(&self, query: &str) -> Vec<f32> {
+        embed_query(self, query)
+    }
 
-pub fn embed<T: TokenizedOutput>(
-    model: &mut dyn EmbeddingModel<TokenizedOutput = T>,
-    text: &[&str],
-) -> Vec<Vec<f32>> {
-    let tokenized_output = model.tokenize(text);
-    return model.embed(tokenized_output);
+    fn embed_documents(&self, documents: &[&str]) -> Vec<Vec<f32>> {
+        embed_documents(self, documents)
+    }
+}
+
+/// Splits a model id such as `BAAI/bge-small-en-v1.5:q8_0` into the hub id
+/// and the requested weight quantization, or fails on an unknown
+/// quantization.
+pub fn parse_model_id(model_id: &str) -> Result<(&str, Option<Quantization>), String> {
+    match model_id.rsplit_once(':') {
+        Some((hub_id, quantization)) => match Quantization::parse(quantization) {
+            Some(quantization) => Ok((hub_id, Some(quantization))),
+            None => Err(format!(
+                "unknown quantization `{}` in model `{}`",
+                quantization, model_id
+            )),
+        },
+        None => Ok((model_id, None)),
+    }
+}
+
+/// Loads an embedding model by its hub id, optionally suffixed with a
+/// quantization (`:q8_0`, `:q4_0`) for the candle BERT backends. Fails on an
+/// unknown model or if its weights cannot be loaded.
+pub fn load_model(model_id: &str) -> Result<Box<dyn Embedder>, String> {
+    #[cfg(feature = "fake")]
+    if let Some(model) = fake::FakeEmbedder::from_model_id(model_id) {
+        return Ok(Box::new(model));
+    }
+
+    let model = match parse_model_id(model_id)? {
+        (MiniLM::MODEL_ID, None) => return Ok(Box::new(MiniLM::new())),
+        (MiniLM::MODEL_ID, quantization) => {
+            BertCandle::new(MiniLM::MODEL_ID, Pooling::Mean, "", "", quantization).map(boxed)
+        }
+        (JinaCandle::MODEL_ID, None) => JinaCandle::new().map(boxed),
+        (BertCandle::BGE_SMALL, quantization) => {
+            BertCandle::bge(BertCandle::BGE_SMALL, quantization).map(boxed)
+        }
+        (BertCandle::E5_SMALL, quantization) => {
+            BertCandle::e5(BertCandle::E5_SMALL, quantization).map(boxed)
+        }
+        _ => return Err(format!("unknown embedding model `{}`", model_id)),
+    };
+    model.map_err(|e| format!("cannot load embedding model `{}`: {}", model_id, e))
+}
+
+fn boxed<M: Embedder + 'static>(model: M) -> Box<dyn Embedder> {
+    Box::new(model)
+}
+
+/// Loads a cross-encoder reranker by its hub id.
+pub fn load_reranker(model_id: &str) -> Result<Box<dyn Reranker>, String> {
+    #[cfg(feature = "fake")]
+    if model_id == fake::FakeReranker::MODEL_ID {
+        return Ok(Box::new(fake::FakeReranker));
+    }
+
+    match CrossEncoder::new(model_id) {
+        Ok(reranker) => Ok(Box::new(reranker)),
+        Err(e) => Err(format!("cannot load reranker `{}`: {}", model_id, e)),
+    }
 }
@@ -0,0 +1,118 @@
+use candle_core::{Error, Result};
+use hf_hub::{api::sync::Api, Repo, RepoType};
+use tokenizers::Tokenizer;
+
+use crate::{parse_model_id, Embedder};
+
+/// How the embeddings of a document's windows are combined.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WindowPooling {
+    /// Plain average of all windows.
+    Mean,
+    /// Average of all windows, blended with the title embedding at the given
+    /// weight (0.0 - 1.0).
+    Title(f32),
+}
+
+#[derive(Clone, Debug)]
+pub struct LongTextOptions {
+    /// Tokens per window. Should not exceed the model's sequence length.
+    pub window: usize,
+    /// Tokens shared between consecutive windows.
+    pub overlap: usize,
+    /// Upper bound on windows embedded per document; later text is dropped.
+    pub max_windows: usize,
+    pub pooling: WindowPooling,
+}
+
+impl Default for LongTextOptions {
+    fn default() -> Self {
+        LongTextOptions {
+            window: 128,
+            overlap: 32,
+            max_windows: 32,
+            pooling: WindowPooling::Mean,
+        }
+    }
+}
+
+/// Embeds documents longer than the model's input limit by splitting them into
+/// overlapping token windows, embedding the windows as one batch and pooling
+/// the results into a single vector.
+pub struct LongText {
+    tokenizer: Tokenizer,
+    options: LongTextOptions,
+}
+
+impl LongText {
+    /// Uses the tokenizer published with `model_id` so that windows line up
+    /// with what the model actually sees.
+    pub fn new(model_id: &str, options: LongTextOptions) -> Result<LongText> {
+        let (hub_id, _) = parse_model_id(model_id).map_err(Error::Msg)?;
+        let api = Api::new().map_err(Error::wrap)?;
+        let tokenizer = api
+            .repo(Repo::new(hub_id.to_string(), RepoType::Model))
+            .get("tokenizer.json")
+            .map_err(Error::wrap)?;
+        let mut tokenizer = Tokenizer::from_file(tokenizer).map_err(|e| Error::Msg(e.to_string()))?;
+        tokenizer
+            .with_padding(None)
+            .with_truncation(None)
+            .map_err(|e| Error::Msg(e.to_string()))?;
+
+        Ok(LongText { tokenizer, options })
+    }
+
+    /// Splits `text` into overlapping slices of at most `window` tokens.
+    pub fn windows<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let encoding = self.tokenizer.encode(text, false).unwrap();
+        let offsets = encoding.get_offsets();
+
+        if offsets.len() <= self.options.window {
+            return vec![text];
+        }
+
+        let stride = self.options.window.saturating_sub(self.options.overlap).max(1);
+
+        (0..offsets.len())
+            .step_by(stride)
+            .take(self.options.max_windows)
+            .take_while(|start| *start == 0 || start + self.options.overlap < offsets.len())
+            .map(|start| {
+                let end = (start + self.options.window).min(offsets.len()) - 1;
+                &text[offsets[start].0..offsets[end].1]
+            })
+            .collect::<Vec<_>>()
+    }
+
+    pub fn embed_document(&self, model: &dyn Embedder, title: &str, text: &str) -> Vec<f32> {
+        let windows = self.windows(text);
+        let embeddings = model.embed_documents(&windows);
+
+        let mut pooled = vec![0.0; model.dimensions()];
+        for embedding in embeddings.iter() {
+            for (p, e) in pooled.iter_mut().zip(embedding) {
+                *p += e / embeddings.len() as f32;
+            }
+        }
+
+        if let WindowPooling::Title(weight) = self.options.pooling {
+            if !title.is_empty() {
+                let title = model.embed_documents(&[title]).remove(0);
+                for (p, t) in pooled.iter_mut().zip(title) {
+                    *p = (1.0 - weight) * *p + weight * t;
+                }
+            }
+        }
+
+        normalize(&mut pooled);
+        pooled
+    }
+}
+
+fn normalize(v: &mut [f32]) {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+}
@@ -25,6 +25,8 @@ pub struct NullTokens {
 impl TokenizedOutput for NullTokens {}
 
 impl MiniLM {
+    pub const MODEL_ID: &'static str = "sentence-transformers/all-MiniLM-L12-v2";
+
     pub fn new() -> MiniLM {
         let model = SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL12V2)
             .create_model()
@@ -55,4 +57,12 @@ impl EmbeddingModel for MiniLM {
             )
             .unwrap()
     }
+
+    fn model_id(self: &Self) -> &str {
+        MiniLM::MODEL_ID
+    }
+
+    fn dimensions(self: &Self) -> usize {
+        384
+    }
 }
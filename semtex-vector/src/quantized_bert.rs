@@ -0,0 +1,242 @@
+use std::path::{Path, PathBuf};
+
+use candle_core::quantized::{gguf_file, k_quants, QMatMul, QTensor};
+use candle_core::{Device, Module, Result, Tensor};
+use candle_nn::{Embedding, LayerNorm};
+use candle_transformers::quantized_var_builder::VarBuilder;
+use serde::Deserialize;
+
+/// Weight format for the linear layers of a quantized model. Embeddings and
+/// layer norms always stay in F32.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Quantization {
+    Q8_0,
+    Q4_0,
+}
+
+impl Quantization {
+    pub fn parse(name: &str) -> Option<Quantization> {
+        match name {
+            "q8_0" => Some(Quantization::Q8_0),
+            "q4_0" => Some(Quantization::Q4_0),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Quantization::Q8_0 => "q8_0",
+            Quantization::Q4_0 => "q4_0",
+        }
+    }
+
+    fn quantize(&self, tensor: &Tensor) -> Result<QTensor> {
+        match self {
+            Quantization::Q8_0 => QTensor::quantize::<k_quants::BlockQ8_0>(tensor),
+            Quantization::Q4_0 => QTensor::quantize::<k_quants::BlockQ4_0>(tensor),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Config {
+    vocab_size: usize,
+    hidden_size: usize,
+    num_hidden_layers: usize,
+    num_attention_heads: usize,
+    max_position_embeddings: usize,
+    type_vocab_size: usize,
+    layer_norm_eps: f64,
+}
+
+/// Converts BERT safetensors weights to a GGUF file, quantizing the linear
+/// layers. The result is written next to the source weights and reused on
+/// later loads.
+pub fn quantize_safetensors(safetensors: &Path, quantization: Quantization) -> Result<PathBuf> {
+    let gguf = safetensors.with_file_name(format!("model.{}.gguf", quantization.name()));
+    if gguf.exists() {
+        return Ok(gguf);
+    }
+
+    let tensors = candle_core::safetensors::load(safetensors, &Device::Cpu)?;
+    let mut quantized = Vec::new();
+    for (name, tensor) in tensors.iter() {
+        if !tensor.dtype().is_float() {
+            continue;
+        }
+
+        let name = name.strip_prefix("bert.").unwrap_or(name);
+        let is_linear = tensor.rank() == 2 && !name.starts_with("embeddings.");
+        let tensor = if is_linear {
+            quantization.quantize(tensor)?
+        } else {
+            QTensor::quantize::<f32>(tensor)?
+        };
+        quantized.push((name.to_string(), tensor));
+    }
+
+    let tmp = gguf.with_extension("gguf.tmp");
+    let mut file = std::fs::File::create(&tmp)?;
+    gguf_file::write(
+        &mut file,
+        &[],
+        &quantized
+            .iter()
+            .map(|(name, tensor)| (name.as_str(), tensor))
+            .collect::<Vec<_>>(),
+    )?;
+    std::fs::rename(tmp, &gguf)?;
+
+    Ok(gguf)
+}
+
+fn linear(vb: &VarBuilder) -> Result<Linear> {
+    let weight = vb.get_no_shape("weight")?;
+    let bias = vb.get_no_shape("bias")?.dequantize(vb.device())?;
+    Ok(Linear {
+        weight: QMatMul::from_arc(weight)?,
+        bias,
+    })
+}
+
+fn embedding(vb: &VarBuilder, rows: usize, hidden_size: usize) -> Result<Embedding> {
+    let weight = vb.get((rows, hidden_size), "weight")?.dequantize(vb.device())?;
+    Ok(Embedding::new(weight, hidden_size))
+}
+
+fn layer_norm(vb: &VarBuilder, config: &Config) -> Result<LayerNorm> {
+    let weight = vb.get(config.hidden_size, "weight")?.dequantize(vb.device())?;
+    let bias = vb.get(config.hidden_size, "bias")?.dequantize(vb.device())?;
+    Ok(LayerNorm::new(weight, bias, config.layer_norm_eps))
+}
+
+struct Linear {
+    weight: QMatMul,
+    bias: Tensor,
+}
+
+impl Module for Linear {
+    fn forward(&self, xs: &Tensor) -> Result<Tensor> {
+        xs.apply(&self.weight)?.broadcast_add(&self.bias)
+    }
+}
+
+struct Layer {
+    query: Linear,
+    key: Linear,
+    value: Linear,
+    attention_output: Linear,
+    attention_norm: LayerNorm,
+    intermediate: Linear,
+    output: Linear,
+    output_norm: LayerNorm,
+    num_attention_heads: usize,
+}
+
+impl Layer {
+    fn load(vb: &VarBuilder, config: &Config) -> Result<Layer> {
+        Ok(Layer {
+            query: linear(&vb.pp("attention.self.query"))?,
+            key: linear(&vb.pp("attention.self.key"))?,
+            value: linear(&vb.pp("attention.self.value"))?,
+            attention_output: linear(&vb.pp("attention.output.dense"))?,
+            attention_norm: layer_norm(&vb.pp("attention.output.LayerNorm"), config)?,
+            intermediate: linear(&vb.pp("intermediate.dense"))?,
+            output: linear(&vb.pp("output.dense"))?,
+            output_norm: layer_norm(&vb.pp("output.LayerNorm"), config)?,
+            num_attention_heads: config.num_attention_heads,
+        })
+    }
+
+    fn heads(&self, xs: &Tensor) -> Result<Tensor> {
+        let (batch, seq, hidden) = xs.dims3()?;
+        xs.reshape((batch, seq, self.num_attention_heads, hidden / self.num_attention_heads))?
+            .transpose(1, 2)?
+            .contiguous()
+    }
+
+    fn forward(&self, xs: &Tensor) -> Result<Tensor> {
+        let (batch, seq, hidden) = xs.dims3()?;
+        let head_size = hidden / self.num_attention_heads;
+
+        let query = self.heads(&self.query.forward(xs)?)?;
+        let key = self.heads(&self.key.forward(xs)?)?;
+        let value = self.heads(&self.value.forward(xs)?)?;
+
+        let scores = (query.matmul(&key.t()?)? / (head_size as f64).sqrt())?;
+        let probs = candle_nn::ops::softmax_last_dim(&scores)?;
+        let context = probs
+            .matmul(&value)?
+            .transpose(1, 2)?
+            .contiguous()?
+            .reshape((batch, seq, hidden))?;
+
+        let attention = self
+            .attention_norm
+            .forward(&(self.attention_output.forward(&context)? + xs)?)?;
+        let intermediate = self.intermediate.forward(&attention)?.gelu_erf()?;
+        self.output_norm
+            .forward(&(self.output.forward(&intermediate)? + attention)?)
+    }
+}
+
+/// BERT encoder with quantized linear layers, for faster CPU inference. Like
+/// the candle `BertModel` it has no attention mask, so inputs are unpadded.
+pub struct QuantizedBertModel {
+    word_embeddings: Embedding,
+    position_embeddings: Embedding,
+    token_type_embeddings: Embedding,
+    embeddings_norm: LayerNorm,
+    layers: Vec<Layer>,
+    pub device: Device,
+}
+
+impl QuantizedBertModel {
+    pub fn load(gguf: &Path, config: &str) -> Result<QuantizedBertModel> {
+        let config: Config = serde_json::from_str(config).map_err(candle_core::Error::wrap)?;
+        let vb = VarBuilder::from_gguf(gguf)?;
+
+        let layers = (0..config.num_hidden_layers)
+            .map(|i| Layer::load(&vb.pp(format!("encoder.layer.{i}")), &config))
+            .collect::<Result<Vec<_>>>()?;
+
+        let embeddings = vb.pp("embeddings");
+        Ok(QuantizedBertModel {
+            word_embeddings: embedding(
+                &embeddings.pp("word_embeddings"),
+                config.vocab_size,
+                config.hidden_size,
+            )?,
+            position_embeddings: embedding(
+                &embeddings.pp("position_embeddings"),
+                config.max_position_embeddings,
+                config.hidden_size,
+            )?,
+            token_type_embeddings: embedding(
+                &embeddings.pp("token_type_embeddings"),
+                config.type_vocab_size,
+                config.hidden_size,
+            )?,
+            embeddings_norm: layer_norm(&embeddings.pp("LayerNorm"), &config)?,
+            layers,
+            device: vb.device().clone(),
+        })
+    }
+
+    pub fn forward(&self, input_ids: &Tensor, token_type_ids: &Tensor) -> Result<Tensor> {
+        let (_batch, seq) = input_ids.dims2()?;
+        let position_ids = Tensor::arange(0u32, seq as u32, input_ids.device())?;
+
+        let embeddings = self
+            .word_embeddings
+            .forward(input_ids)?
+            .add(&self.token_type_embeddings.forward(token_type_ids)?)?
+            .broadcast_add(&self.position_embeddings.forward(&position_ids)?)?;
+
+        let mut hidden = self.embeddings_norm.forward(&embeddings)?;
+        for layer in self.layers.iter() {
+            hidden = layer.forward(&hidden)?;
+        }
+        Ok(hidden)
+    }
+}
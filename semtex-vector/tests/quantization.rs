@@ -0,0 +1,152 @@
+//! Compares quantized candle backends against their full-precision weights.
+//!
+//! Downloads models from the hub, so it is ignored by default:
+//!
+//!     cargo test --release -p semtex-vector --test quantization -- --ignored --nocapture
+
+use std::time::Instant;
+
+use semtex_vector::{load_model, Embedder};
+
+const DOCUMENTS: &[&str] = &[
+    "Rust's ownership model guarantees memory safety without a garbage collector.",
+    "Async functions in Rust return futures that do nothing until they are polled.",
+    "Dropping a future cancels it, so cancellation safety matters in select! loops.",
+    "Kubernetes schedules pods onto nodes based on resource requests and limits.",
+    "A CVE in the container runtime allowed escaping to the host filesystem.",
+    "Helm charts package Kubernetes manifests with templated configuration values.",
+    "Sourdough bread relies on wild yeast and lactic acid bacteria for leavening.",
+    "Proofing dough overnight in the fridge develops a more complex flavour.",
+    "The Hubble constant describes how fast the universe is expanding.",
+    "Dark energy is thought to drive the accelerating expansion of the universe.",
+    "SQLite stores an entire database in a single cross-platform file.",
+    "Write-ahead logging lets SQLite readers proceed while a writer commits.",
+    "HNSW graphs give approximate nearest neighbour search in logarithmic time.",
+    "Product quantization compresses vectors by splitting them into sub-spaces.",
+    "Marathon training plans increase weekly mileage by about ten percent.",
+    "Interval sessions improve VO2 max more than steady long runs do.",
+];
+
+const QUERIES: &[&str] = &[
+    "how does rust cancel async tasks",
+    "container escape vulnerability",
+    "baking bread with a starter",
+    "why is the universe expanding faster",
+    "concurrent reads in sqlite",
+    "approximate vector search index",
+    "how to train for a long distance race",
+    "deploying apps on kubernetes",
+];
+
+const K: usize = 3;
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    dot / (norm(a) * norm(b))
+}
+
+fn top_k(query: &[f32], documents: &[Vec<f32>]) -> Vec<usize> {
+    let mut ranked = (0..documents.len()).collect::<Vec<_>>();
+    ranked.sort_by(|a, b| {
+        cosine(query, &documents[*b])
+            .partial_cmp(&cosine(query, &documents[*a]))
+            .unwrap()
+    });
+    ranked.truncate(K);
+    ranked
+}
+
+struct Run {
+    documents: Vec<Vec<f32>>,
+    queries: Vec<Vec<f32>>,
+    millis_per_document: f64,
+}
+
+fn run(model: &dyn Embedder) -> Run {
+    // Warm up so that lazy initialisation is not counted.
+    model.embed_documents(&DOCUMENTS[..1]);
+
+    let start = Instant::now();
+    let documents = model.embed_documents(DOCUMENTS);
+    let millis_per_document = start.elapsed().as_secs_f64() * 1000.0 / DOCUMENTS.len() as f64;
+
+    let queries = QUERIES.iter().map(|q| model.embed_query(q)).collect::<Vec<_>>();
+
+    Run {
+        documents,
+        queries,
+        millis_per_document,
+    }
+}
+
+/// Returns how many times faster the quantized model embeds documents.
+fn compare(hub_id: &str, quantization: &str, min_cosine: f32, min_recall: f32) -> f64 {
+    let reference = run(load_model(hub_id).unwrap().as_ref());
+    let quantized = run(load_model(&format!("{}:{}", hub_id, quantization)).unwrap().as_ref());
+
+    let mean_cosine = reference
+        .documents
+        .iter()
+        .zip(quantized.documents.iter())
+        .map(|(a, b)| cosine(a, b))
+        .sum::<f32>()
+        / DOCUMENTS.len() as f32;
+
+    let recall = reference
+        .queries
+        .iter()
+        .zip(quantized.queries.iter())
+        .map(|(r, q)| {
+            let expected = top_k(r, &reference.documents);
+            let actual = top_k(q, &quantized.documents);
+            expected.iter().filter(|d| actual.contains(d)).count() as f32 / K as f32
+        })
+        .sum::<f32>()
+        / QUERIES.len() as f32;
+
+    println!(
+        "{:<40} {:>6}  f32 {:>7.2} ms/doc  {} {:>7.2} ms/doc  speedup {:>5.2}x  cosine {:.4}  recall@{} {:.3}",
+        hub_id,
+        quantization,
+        reference.millis_per_document,
+        quantization,
+        quantized.millis_per_document,
+        reference.millis_per_document / quantized.millis_per_document,
+        mean_cosine,
+        K,
+        recall
+    );
+
+    assert!(mean_cosine >= min_cosine, "mean cosine {} < {}", mean_cosine, min_cosine);
+    assert!(recall >= min_recall, "recall@{} {} < {}", K, recall, min_recall);
+    reference.millis_per_document / quantized.millis_per_document
+}
+
+#[test]
+#[ignore]
+fn bge_small_q8_0() {
+    let speedup = compare("BAAI/bge-small-en-v1.5", "q8_0", 0.99, 0.9);
+    assert!(
+        speedup > 1.0,
+        "q8_0 is {:.2}x slower than f32",
+        1.0 / speedup
+    );
+}
+
+#[test]
+#[ignore]
+fn e5_small_q8_0() {
+    let speedup = compare("intfloat/e5-small-v2", "q8_0", 0.99, 0.9);
+    assert!(
+        speedup > 1.0,
+        "q8_0 is {:.2}x slower than f32",
+        1.0 / speedup
+    );
+}
+
+#[test]
+#[ignore]
+fn bge_small_q4_0() {
+    compare("BAAI/bge-small-en-v1.5", "q4_0", 0.9, 0.7);
+}
@@ -0,0 +1,80 @@
+//! Checks the quantized BERT encoder against candle's `BertModel` on a tiny
+//! randomly initialised checkpoint, so nothing is downloaded.
+
+use candle_core::{DType, Device, Tensor};
+use candle_nn::{VarBuilder, VarMap};
+use candle_transformers::models::bert::{BertModel, Config};
+use semtex_vector::quantized_bert::{quantize_safetensors, Quantization, QuantizedBertModel};
+use tempfile::TempDir;
+
+// Hidden sizes are multiples of the 32-value quantization block.
+const CONFIG: &str = r#"{
+    "vocab_size": 100,
+    "hidden_size": 64,
+    "num_hidden_layers": 2,
+    "num_attention_heads": 4,
+    "intermediate_size": 128,
+    "hidden_act": "gelu",
+    "hidden_dropout_prob": 0.1,
+    "max_position_embeddings": 32,
+    "type_vocab_size": 2,
+    "initializer_range": 0.02,
+    "layer_norm_eps": 1e-12,
+    "pad_token_id": 0,
+    "classifier_dropout": null,
+    "model_type": "bert"
+}"#;
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    dot / (norm(a) * norm(b))
+}
+
+/// Mean cosine similarity between the token embeddings of the full-precision
+/// and the quantized encoder.
+fn compare(quantization: Quantization) -> f32 {
+    let dir = TempDir::new().unwrap();
+    let safetensors = dir.path().join("model.safetensors");
+
+    let weights = VarMap::new();
+    let vb = VarBuilder::from_varmap(&weights, DType::F32, &Device::Cpu);
+    let config: Config = serde_json::from_str(CONFIG).unwrap();
+    let reference = BertModel::load(vb, &config).unwrap();
+    weights.save(&safetensors).unwrap();
+
+    let gguf = quantize_safetensors(&safetensors, quantization).unwrap();
+    assert_eq!(
+        gguf,
+        dir.path()
+            .join(format!("model.{}.gguf", quantization.name()))
+    );
+    let quantized = QuantizedBertModel::load(&gguf, CONFIG).unwrap();
+
+    let input_ids = Tensor::new(&[[1u32, 17, 42, 99, 3, 2]], &Device::Cpu).unwrap();
+    let token_type_ids = input_ids.zeros_like().unwrap();
+    let expected = reference.forward(&input_ids, &token_type_ids).unwrap();
+    let actual = quantized.forward(&input_ids, &token_type_ids).unwrap();
+    assert_eq!(actual.dims(), expected.dims());
+
+    let expected = expected.squeeze(0).unwrap().to_vec2::<f32>().unwrap();
+    let actual = actual.squeeze(0).unwrap().to_vec2::<f32>().unwrap();
+    expected
+        .iter()
+        .zip(actual.iter())
+        .map(|(e, a)| cosine(e, a))
+        .sum::<f32>()
+        / expected.len() as f32
+}
+
+#[test]
+fn q8_0_matches_full_precision() {
+    let similarity = compare(Quantization::Q8_0);
+    assert!(similarity > 0.995, "mean cosine {}", similarity);
+}
+
+#[test]
+fn q4_0_stays_close_to_full_precision() {
+    let similarity = compare(Quantization::Q4_0);
+    assert!(similarity > 0.9, "mean cosine {}", similarity);
+}